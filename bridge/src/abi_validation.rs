@@ -0,0 +1,160 @@
+// Copyright 2017 Parity Technologies (UK) Ltd.
+// This file is part of Parity-Bridge.
+
+// Parity-Bridge is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity-Bridge is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity-Bridge.  If not, see <http://www.gnu.org/licenses/>.
+
+//! checking that the functions of an ABI exist in deployed contract code
+
+use error::{self, ResultExt};
+use ethabi;
+use futures::Future;
+use std::collections::HashSet;
+use std::time::Duration;
+use tiny_keccak;
+use tokio_timer::Timer;
+use web3;
+use web3::types::Address;
+use web3::Transport;
+
+/// ABI of the `MainBridge` contract this bridge was compiled with
+pub const MAIN_ABI: &str = include_str!("../../compiled_contracts/MainBridge.abi");
+/// ABI of the `SideBridge` contract this bridge was compiled with
+pub const SIDE_ABI: &str = include_str!("../../compiled_contracts/SideBridge.abi");
+
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+
+/// result of comparing an ABI to deployed contract code
+#[derive(Debug, PartialEq, Clone)]
+pub enum AbiValidation {
+    /// the selectors of all ABI functions were found in the code
+    Valid,
+    /// signatures of the ABI functions whose selectors were not found in the code
+    MissingFunctions(Vec<String>),
+    /// there is no code at the contract address.
+    /// the contract has self-destructed or was never deployed
+    NoCode,
+}
+
+/// the 4 byte selectors pushed onto the stack by `PUSH4` opcodes in `code`.
+/// the solidity function dispatcher compares the call data against
+/// each function selector pushed that way.
+pub fn push4_selectors(code: &[u8]) -> HashSet<[u8; 4]> {
+    let mut selectors = HashSet::new();
+    let mut i = 0;
+    while i < code.len() {
+        let opcode = code[i];
+        if opcode == PUSH4 && i + 4 < code.len() {
+            let mut selector = [0u8; 4];
+            selector.copy_from_slice(&code[i + 1..i + 5]);
+            selectors.insert(selector);
+        }
+        if opcode >= PUSH1 && opcode <= PUSH32 {
+            // skip over the pushed data so it isn't mistaken for opcodes
+            i += (opcode - PUSH1) as usize + 1;
+        }
+        i += 1;
+    }
+    selectors
+}
+
+/// signature of `function` as used to compute its selector. example: `withdraw(uint8[],bytes32[],bytes32[],bytes)`
+fn function_signature(function: &ethabi::Function) -> String {
+    let params = function
+        .inputs
+        .iter()
+        .map(|param| format!("{}", param.kind))
+        .collect::<Vec<_>>();
+    format!("{}({})", function.name, params.join(","))
+}
+
+/// checks that the selector of every function in `abi` is pushed somewhere in `code`
+pub fn validate_abi(abi: &ethabi::Contract, code: &[u8]) -> AbiValidation {
+    if code.is_empty() {
+        return AbiValidation::NoCode;
+    }
+    let selectors = push4_selectors(code);
+    let mut missing = abi.functions()
+        .map(function_signature)
+        .filter(|signature| {
+            let hash = tiny_keccak::keccak256(signature.as_bytes());
+            let mut selector = [0u8; 4];
+            selector.copy_from_slice(&hash[..4]);
+            !selectors.contains(&selector)
+        })
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        AbiValidation::Valid
+    } else {
+        missing.sort();
+        AbiValidation::MissingFunctions(missing)
+    }
+}
+
+/// returns a `Future` that fetches the code deployed at `contract_address`
+/// and resolves with the result of validating `abi_json` against it
+pub fn validate_abi_against_deployed_code<T: Transport>(
+    transport: &T,
+    contract_address: Address,
+    abi_json: &str,
+    timeout: Duration,
+) -> Result<impl Future<Item = AbiValidation, Error = error::Error>, error::Error> {
+    let abi = ethabi::Contract::load(abi_json.as_bytes()).chain_err(|| "Cannot parse ABI")?;
+    let future = web3::api::Eth::new(transport)
+        .code(contract_address, None)
+        .from_err();
+    Ok(Timer::default()
+        .timeout(future, timeout)
+        .map(move |code| validate_abi(&abi, &code.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABI: &str = r#"[
+        {"constant":true,"inputs":[],"name":"isMainBridgeContract","outputs":[{"name":"","type":"bool"}],"payable":false,"stateMutability":"pure","type":"function"},
+        {"constant":false,"inputs":[{"name":"recipient","type":"address"},{"name":"value","type":"uint256"}],"name":"transfer","outputs":[],"payable":false,"stateMutability":"nonpayable","type":"function"}
+    ]"#;
+
+    #[test]
+    fn test_push4_selectors_skips_push_data() {
+        // PUSH2 0x63aa, PUSH4 0x11223344, STOP
+        let code = [0x61, 0x63, 0xaa, 0x63, 0x11, 0x22, 0x33, 0x44, 0x00];
+        let selectors = push4_selectors(&code);
+        assert_eq!(selectors.len(), 1);
+        assert!(selectors.contains(&[0x11, 0x22, 0x33, 0x44]));
+    }
+
+    #[test]
+    fn test_validate_abi() {
+        let abi = ethabi::Contract::load(ABI.as_bytes()).unwrap();
+
+        // keccak256("isMainBridgeContract()")[..4]
+        let is_main_bridge_contract = tiny_keccak::keccak256(b"isMainBridgeContract()");
+        // keccak256("transfer(address,uint256)")[..4] = 0xa9059cbb
+        let mut code = vec![PUSH4, 0xa9, 0x05, 0x9c, 0xbb, 0x14, PUSH4];
+        code.extend_from_slice(&is_main_bridge_contract[..4]);
+        code.push(0x00);
+
+        assert_eq!(validate_abi(&abi, &code), AbiValidation::Valid);
+        assert_eq!(
+            validate_abi(&abi, &code[..6]),
+            AbiValidation::MissingFunctions(vec!["isMainBridgeContract()".into()])
+        );
+        assert_eq!(validate_abi(&abi, &[]), AbiValidation::NoCode);
+    }
+}
@@ -16,7 +16,7 @@
 
 use std::time::Duration;
 use error::{self, ResultExt};
-use tokio_timer::{Interval, Timeout, Timer};
+use tokio_timer::{Sleep, Timeout, Timer};
 use futures::future::FromErr;
 use futures::{Async, Future, Poll, Stream};
 use web3;
@@ -27,7 +27,7 @@ use web3::types::U256;
 
 /// Block Number Stream state.
 enum State<T: Transport> {
-    AwaitInterval,
+    AwaitInterval(Sleep),
     AwaitBlockNumber(Timeout<FromErr<CallFuture<U256, T::Out>, error::Error>>),
 }
 
@@ -46,7 +46,7 @@ pub struct BlockNumberStream<T: Transport> {
     transport: T,
     last_checked_block: u64,
     timer: Timer,
-    poll_interval: Interval,
+    poll_interval: Duration,
     state: State<T>,
 }
 
@@ -57,13 +57,19 @@ impl<T: Transport> BlockNumberStream<T> {
         BlockNumberStream {
             request_timeout: options.request_timeout,
             confirmations: options.confirmations,
-            poll_interval: timer.interval(options.poll_interval),
+            poll_interval: options.poll_interval,
             transport: options.transport,
             last_checked_block: options.after,
+            state: State::AwaitInterval(timer.sleep(options.poll_interval)),
             timer,
-            state: State::AwaitInterval,
         }
     }
+
+    /// change how long to wait between polls.
+    /// takes effect after the currently running wait.
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
 }
 
 impl<T: Transport> Stream for BlockNumberStream<T> {
@@ -73,9 +79,9 @@ impl<T: Transport> Stream for BlockNumberStream<T> {
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
         loop {
             let (next_state, value_to_yield) = match self.state {
-                State::AwaitInterval => {
+                State::AwaitInterval(ref mut sleep) => {
                     // wait until `interval` has passed
-                    let _ = try_stream!(self.poll_interval.poll().chain_err(|| format!(
+                    try_ready!(sleep.poll().chain_err(|| format!(
                         "BlockNumberStream polling interval failed",
                     )));
                     info!("BlockNumberStream polling last block number");
@@ -95,12 +101,13 @@ impl<T: Transport> Stream for BlockNumberStream<T> {
                     // subtraction that saturates at zero
                     let last_confirmed_block = last_block.saturating_sub(self.confirmations as u64);
 
+                    let next_state = State::AwaitInterval(self.timer.sleep(self.poll_interval));
                     if self.last_checked_block < last_confirmed_block {
                         self.last_checked_block = last_confirmed_block;
-                        (State::AwaitInterval, Some(last_confirmed_block))
+                        (next_state, Some(last_confirmed_block))
                     } else {
                         info!("BlockNumberStream: no blocks confirmed since we last checked. waiting some more");
-                        (State::AwaitInterval, None)
+                        (next_state, None)
                     }
                 }
             };
@@ -16,13 +16,21 @@
 
 //! concerning reading/writing `State` from/to toml file
 
+use config::ExistingContract;
 use error::{Error, ErrorKind, ResultExt};
+use futures::Future;
+use futures::future::join_all;
+use helpers::percentile;
 use std::io::{Read, Write};
 /// the state of a bridge node process and ways to persist it
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{fmt, fs, io, str};
+use tokio_timer::Timer;
 use toml;
-use web3::types::{Address, TransactionReceipt};
+use web3;
+use web3::types::{Address, BlockId, BlockNumber, TransactionReceipt};
+use web3::Transport;
 
 /// bridge process state
 #[derive(Debug, PartialEq, Deserialize, Serialize, Default, Clone)]
@@ -72,6 +80,121 @@ impl State {
             last_side_to_main_signatures_at_block: side_block_number,
         }
     }
+
+    /// creates initial state for the bridge processes
+    /// from contracts that were deployed before this node joined the bridge
+    pub fn from_existing_contracts(main: &ExistingContract, side: &ExistingContract) -> Self {
+        Self {
+            main_contract_address: main.address,
+            side_contract_address: side.address,
+            main_deployed_at_block: main.deployed_at_block,
+            side_deployed_at_block: side.deployed_at_block,
+            last_main_to_side_sign_at_block: main.deployed_at_block,
+            last_side_to_main_sign_at_block: side.deployed_at_block,
+            last_side_to_main_signatures_at_block: side.deployed_at_block,
+        }
+    }
+}
+
+impl State {
+    /// returns a `Future` that fails if the contracts weren't deployed at
+    /// `main_deployed_at_block` and `side_deployed_at_block` on the chains
+    /// `main_transport` and `side_transport` are connected to.
+    /// this happens if the deployment got reorganized away or the database
+    /// belongs to a different chain.
+    /// only blocks and receipts are fetched, no historical state, so this
+    /// works with pruned nodes. if a node doesn't have the deploy block
+    /// or its receipts a warning is logged instead of failing.
+    pub fn verify_against_chain<T: Transport>(
+        &self,
+        main_transport: &T,
+        main_timeout: Duration,
+        side_transport: &T,
+        side_timeout: Duration,
+    ) -> impl Future<Item = (), Error = Error> {
+        verify_deployment(
+            main_transport,
+            "main",
+            self.main_contract_address,
+            self.main_deployed_at_block,
+            main_timeout,
+        ).join(verify_deployment(
+            side_transport,
+            "side",
+            self.side_contract_address,
+            self.side_deployed_at_block,
+            side_timeout,
+        ))
+            .map(|_| ())
+    }
+}
+
+/// returns a `Future` that resolves if one of the contract creation transactions
+/// in block `deployed_at_block` created `contract_address`
+fn verify_deployment<T: Transport>(
+    transport: &T,
+    chain: &'static str,
+    contract_address: Address,
+    deployed_at_block: u64,
+    timeout: Duration,
+) -> impl Future<Item = (), Error = Error> {
+    let transport = transport.clone();
+    let block = web3::api::Eth::new(&transport)
+        .block_with_txs(BlockId::Number(BlockNumber::Number(deployed_at_block)))
+        .from_err();
+    Timer::default()
+        .timeout(block, timeout)
+        .and_then(move |block| {
+            // `None` if the node doesn't have the block
+            let creations = block.map(|block| {
+                block
+                    .transactions
+                    .into_iter()
+                    .filter(|transaction| transaction.to.is_none())
+                    .map(|transaction| transaction.hash)
+                    .collect::<Vec<_>>()
+            });
+            let receipts = creations
+                .iter()
+                .flat_map(|hashes| hashes.iter())
+                .map(|hash| {
+                    let receipt = web3::api::Eth::new(&transport)
+                        .transaction_receipt(*hash)
+                        .from_err();
+                    Timer::default().timeout(receipt, timeout)
+                })
+                .collect::<Vec<_>>();
+            join_all(receipts).map(move |receipts| (creations.is_some(), receipts))
+        })
+        .and_then(move |(has_block, receipts)| {
+            if !has_block {
+                warn!(
+                    "{} node doesn't have block {}. can't verify the deployment of contract {:?}",
+                    chain, deployed_at_block, contract_address
+                );
+                return Ok(());
+            }
+            let created = receipts
+                .iter()
+                .filter_map(|receipt| receipt.as_ref())
+                .any(|receipt| receipt.contract_address == Some(contract_address));
+            if created {
+                return Ok(());
+            }
+            if receipts.iter().any(|receipt| receipt.is_none()) {
+                warn!(
+                    "{} node is missing receipts of block {}. can't verify the deployment of contract {:?}",
+                    chain, deployed_at_block, contract_address
+                );
+                return Ok(());
+            }
+            bail!(
+                "{} contract {:?} wasn't created in block {}. the deployment may have been reorganized away or the database belongs to a different chain",
+                chain,
+                contract_address,
+                deployed_at_block
+            );
+        })
 }
 
 impl State {
@@ -122,6 +245,18 @@ impl TomlFileDatabase {
             state,
         })
     }
+
+    /// like `from_path` but starts out with `default` if there is no file at `filepath`.
+    /// the file is created on the first `write` of a changed state.
+    pub fn load_or_default<P: AsRef<Path>>(filepath: P, default: State) -> Result<Self, Error> {
+        match Self::from_path(&filepath) {
+            Err(Error(ErrorKind::MissingFile(_), _)) => Ok(Self {
+                filepath: filepath.as_ref().to_path_buf(),
+                state: default,
+            }),
+            result => result,
+        }
+    }
 }
 
 impl Database for TomlFileDatabase {
@@ -143,3 +278,259 @@ impl Database for TomlFileDatabase {
         Ok(())
     }
 }
+
+/// name of the file that is written next to the database by `emergency_shutdown`
+pub const EMERGENCY_STOP_FILENAME: &str = "BRIDGE_EMERGENCY_STOP";
+
+/// path of the emergency stop file for the database at `database_path`
+pub fn emergency_stop_path<P: AsRef<Path>>(database_path: P) -> PathBuf {
+    database_path
+        .as_ref()
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(EMERGENCY_STOP_FILENAME)
+}
+
+/// writes the emergency stop file next to the database at `database_path`
+/// containing `reason` and the current unix timestamp.
+/// `check_emergency_stop` refuses to let the bridge start until
+/// an operator has manually deleted that file.
+pub fn emergency_shutdown<P: AsRef<Path>>(database_path: P, reason: &str) -> Result<(), Error> {
+    let path = emergency_stop_path(database_path);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    error!("emergency shutdown: {}. writing {:?}", reason, path);
+    let mut file = fs::File::create(&path).chain_err(|| "Cannot write emergency stop file")?;
+    write!(file, "timestamp = {}\nreason = {:?}\n", timestamp, reason)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// fails with `ErrorKind::EmergencyStop` if `emergency_shutdown`
+/// has been called for the database at `database_path`
+/// and the emergency stop file hasn't been deleted since.
+pub fn check_emergency_stop<P: AsRef<Path>>(database_path: P) -> Result<(), Error> {
+    let path = emergency_stop_path(database_path);
+    let mut file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).chain_err(|| "Cannot open emergency stop file"),
+    };
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Err(ErrorKind::EmergencyStop(format!("{:?}", path), contents).into())
+}
+
+/// name of the file that is written next to the database by `benchmark_storage_io`
+const STORAGE_BENCHMARK_FILENAME: &str = "BRIDGE_STORAGE_BENCHMARK";
+
+/// latencies of writing and reading the database
+#[derive(Debug, PartialEq, Clone)]
+pub struct StorageBenchmark {
+    pub median_write: Duration,
+    pub p99_write: Duration,
+    pub median_read: Duration,
+    pub p99_read: Duration,
+}
+
+/// writes `state` to a temporary file next to `database_path` and reads it
+/// back `iterations` times. writes are synced to disk.
+/// the file at `database_path` is neither read nor modified and doesn't need to exist.
+pub fn benchmark_storage_io<P: AsRef<Path>>(
+    state: &State,
+    database_path: P,
+    iterations: u32,
+) -> Result<StorageBenchmark, Error> {
+    if iterations == 0 {
+        bail!("storage benchmark needs at least 1 iteration");
+    }
+    // next to the database so the storage the database is on is measured
+    let path = database_path
+        .as_ref()
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(STORAGE_BENCHMARK_FILENAME);
+
+    let mut writes = Vec::with_capacity(iterations as usize);
+    let mut reads = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let mut file = fs::File::create(&path).chain_err(|| "Cannot write storage benchmark file")?;
+        state.write(&mut file)?;
+        file.sync_all()?;
+        writes.push(start.elapsed());
+
+        let start = Instant::now();
+        TomlFileDatabase::from_path(&path)?;
+        reads.push(start.elapsed());
+    }
+    fs::remove_file(&path)?;
+
+    Ok(StorageBenchmark {
+        median_write: percentile(&mut writes, 50),
+        p99_write: percentile(&mut writes, 99),
+        median_read: percentile(&mut reads, 50),
+        p99_read: percentile(&mut reads, 99),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+    use test::{block_json, receipt_json};
+    use tokio_core::reactor::Core;
+
+    #[test]
+    fn test_emergency_shutdown_prevents_start_until_file_is_deleted() {
+        let dir = TempDir::new("emergency_stop").unwrap();
+        let database_path = dir.path().join("db.toml");
+
+        check_emergency_stop(&database_path).unwrap();
+
+        emergency_shutdown(&database_path, "main contract balance mismatch").unwrap();
+        match *check_emergency_stop(&database_path).unwrap_err().kind() {
+            ErrorKind::EmergencyStop(_, ref contents) => {
+                assert!(contents.contains("main contract balance mismatch"))
+            }
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+
+        fs::remove_file(emergency_stop_path(&database_path)).unwrap();
+        check_emergency_stop(&database_path).unwrap();
+    }
+
+    #[test]
+    fn test_load_or_default() {
+        let tempdir = TempDir::new("load_or_default").unwrap();
+        let database_path = tempdir.path().join("db.toml");
+        let default = State::from_existing_contracts(
+            &ExistingContract {
+                address: "0000000000000000000000000000000000000001".parse().unwrap(),
+                deployed_at_block: 100,
+            },
+            &ExistingContract {
+                address: "0000000000000000000000000000000000000002".parse().unwrap(),
+                deployed_at_block: 200,
+            },
+        );
+
+        let mut database = TomlFileDatabase::load_or_default(&database_path, default.clone()).unwrap();
+        assert_eq!(database.read(), default);
+        assert_eq!(database.read().last_side_to_main_signatures_at_block, 200);
+        assert!(!database_path.exists());
+
+        let mut state = default.clone();
+        state.last_main_to_side_sign_at_block = 120;
+        database.write(&state).unwrap();
+
+        // an existing database takes precedence over the default
+        let database = TomlFileDatabase::load_or_default(&database_path, default).unwrap();
+        assert_eq!(database.read(), state);
+
+        // other errors aren't swallowed
+        fs::File::create(&database_path)
+            .unwrap()
+            .write_all(b"not a database")
+            .unwrap();
+        assert!(TomlFileDatabase::load_or_default(&database_path, State::default()).is_err());
+    }
+
+    #[test]
+    fn test_verify_deployment() {
+        let creation = json!({
+            "hash": "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
+            "nonce": "0x0",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000005",
+            "blockNumber": "0x5",
+            "transactionIndex": "0x0",
+            "from": "0x006b5dda44dc2606f07ad86c9190fb54fd905f6d",
+            "to": null,
+            "value": "0x0",
+            "gasPrice": "0x0",
+            "gas": "0xf4240",
+            "input": "0x6060"
+        });
+        let block = block_json(
+            "0x5",
+            "0x0000000000000000000000000000000000000000000000000000000000000005",
+            "0x0000000000000000000000000000000000000000000000000000000000000004",
+            json!([creation]),
+        );
+        let receipt = |contract_address| {
+            receipt_json(
+                Some(("0x0000000000000000000000000000000000000000000000000000000000000005", "0x5")),
+                "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
+                "0x0",
+                contract_address,
+            )
+        };
+        let contract_address = "0000000000000000000000000000000000000dd1".into();
+        let transport = mock_transport!(
+            "eth_getBlockByNumber" =>
+                req => json!(["0x5", true]),
+                res => block.clone();
+            "eth_getTransactionReceipt" =>
+                req => json!(["0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34"]),
+                res => receipt("0x0000000000000000000000000000000000000dd1");
+            "eth_getBlockByNumber" =>
+                req => json!(["0x5", true]),
+                res => block.clone();
+            "eth_getTransactionReceipt" =>
+                req => json!(["0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34"]),
+                res => receipt("0x0000000000000000000000000000000000000dd2");
+            // a pruned node that doesn't have the block
+            "eth_getBlockByNumber" =>
+                req => json!(["0x5", true]),
+                res => json!(null);
+        );
+
+        let mut event_loop = Core::new().unwrap();
+        let verify = || {
+            verify_deployment(
+                &transport,
+                "main",
+                contract_address,
+                5,
+                Duration::from_secs(1),
+            )
+        };
+        event_loop.run(verify()).unwrap();
+        assert!(event_loop.run(verify()).is_err());
+        event_loop.run(verify()).unwrap();
+
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
+    #[test]
+    fn test_benchmark_storage_io_leaves_database_unchanged() {
+        let dir = TempDir::new("storage_benchmark").unwrap();
+        let database_path = dir.path().join("db.toml");
+        let state = State {
+            main_deployed_at_block: 10,
+            side_deployed_at_block: 20,
+            ..State::default()
+        };
+        state.write(fs::File::create(&database_path).unwrap()).unwrap();
+
+        let benchmark = benchmark_storage_io(&State::default(), &database_path, 5).unwrap();
+        assert!(benchmark.median_write <= benchmark.p99_write);
+        assert!(benchmark.median_read <= benchmark.p99_read);
+
+        assert_eq!(TomlFileDatabase::from_path(&database_path).unwrap().read(), state);
+        assert!(!dir.path().join(STORAGE_BENCHMARK_FILENAME).exists());
+        assert!(benchmark_storage_io(&state, &database_path, 0).is_err());
+    }
+
+    #[test]
+    fn test_benchmark_storage_io_without_database_file() {
+        let dir = TempDir::new("storage_benchmark").unwrap();
+        let database_path = dir.path().join("db.toml");
+
+        benchmark_storage_io(&State::default(), &database_path, 2).unwrap();
+        assert!(!database_path.exists());
+        assert!(!dir.path().join(STORAGE_BENCHMARK_FILENAME).exists());
+    }
+}
@@ -25,10 +25,79 @@ use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
-use web3::types::{TransactionReceipt, TransactionRequest};
+use std::time::Duration;
+use tokio_timer::Timer;
+use web3::helpers::{self, CallFuture};
+use web3::types::{BlockNumber, Bytes, TransactionReceipt, TransactionRequest};
 use web3::Transport;
 use send_tx_with_receipt::{SendTransactionWithReceipt, SendTransactionWithReceiptOptions};
 
+/// transaction that deploys `MainBridge` as configured in `config`
+pub fn main_deploy_request(config: &Config) -> TransactionRequest {
+    let data = contracts::main::constructor(
+        config.main.contract.bin.clone().0,
+        config.authorities.required_signatures,
+        config.authorities.accounts.clone(),
+        config.estimated_gas_cost_of_withdraw,
+        config.max_total_main_contract_balance,
+        config.max_single_deposit_value,
+    );
+
+    TransactionRequest {
+        from: config.address,
+        to: None,
+        gas: Some(config.txs.main_deploy.gas.into()),
+        gas_price: Some(config.txs.main_deploy.gas_price.into()),
+        value: None,
+        data: Some(data.into()),
+        nonce: None,
+        condition: None,
+    }
+}
+
+/// transaction that deploys `SideBridge` as configured in `config`
+pub fn side_deploy_request(config: &Config) -> TransactionRequest {
+    let data = contracts::side::constructor(
+        config.side.contract.bin.clone().0,
+        config.authorities.required_signatures,
+        config.authorities.accounts.clone(),
+        config.estimated_gas_cost_of_withdraw,
+    );
+
+    TransactionRequest {
+        from: config.address,
+        to: None,
+        gas: Some(config.txs.side_deploy.gas.into()),
+        gas_price: Some(config.txs.side_deploy.gas_price.into()),
+        value: None,
+        data: Some(data.into()),
+        nonce: None,
+        condition: None,
+    }
+}
+
+/// simulates the deployment transaction `request` with `eth_call` instead of sending it.
+/// resolves with the code the deployed contract would have.
+/// fails if that code is empty which means the deployment would fail.
+pub fn dry_run_deploy<T: Transport>(
+    transport: &T,
+    request: &TransactionRequest,
+    timeout: Duration,
+) -> impl Future<Item = Bytes, Error = error::Error> {
+    let future: CallFuture<Bytes, T::Out> = CallFuture::new(transport.execute(
+        "eth_call",
+        vec![helpers::serialize(request), helpers::serialize(&BlockNumber::Latest)],
+    ));
+    Timer::default()
+        .timeout(future.from_err(), timeout)
+        .and_then(|code: Bytes| -> Result<Bytes, error::Error> {
+            if code.0.is_empty() {
+                bail!("simulated deployment returned no code. the constructor would fail");
+            }
+            Ok(code)
+        })
+}
+
 pub enum DeployState<T: Transport + Clone> {
     NotDeployed,
     Deploying {
@@ -65,25 +134,12 @@ impl<T: Transport + Clone> Future for DeployMain<T> {
             let next_state = match self.state {
                 DeployState::Deployed { ref contract } => return Ok(contract.clone().into()),
                 DeployState::NotDeployed => {
-                    let data = contracts::main::constructor(
-                        self.config.main.contract.bin.clone().0,
-                        self.config.authorities.required_signatures,
-                        self.config.authorities.accounts.clone(),
-                        self.config.estimated_gas_cost_of_withdraw,
-                        self.config.max_total_main_contract_balance,
-                        self.config.max_single_deposit_value,
-                    );
-
-                    let tx_request = TransactionRequest {
-                        from: self.config.address,
-                        to: None,
-                        gas: Some(self.config.txs.main_deploy.gas.into()),
-                        gas_price: Some(self.config.txs.main_deploy.gas_price.into()),
-                        value: None,
-                        data: Some(data.clone().into()),
-                        nonce: None,
-                        condition: None,
-                    };
+                    let tx_request = main_deploy_request(&self.config);
+                    let data = tx_request
+                        .data
+                        .clone()
+                        .expect("deploy request always has data; qed")
+                        .0;
 
                     let future = SendTransactionWithReceipt::new(SendTransactionWithReceiptOptions {
                         transport: self.main_transport.clone(),
@@ -91,6 +147,8 @@ impl<T: Transport + Clone> Future for DeployMain<T> {
                         poll_interval: self.config.main.poll_interval,
                         confirmations: self.config.main.required_confirmations,
                         transaction: tx_request,
+                        max_retries: self.config.txs.main_deploy.max_retries,
+                        retry_delay: self.config.txs.main_deploy.retry_delay,
                     });
 
                     info!("sending MainBridge contract deployment transaction and waiting for {} confirmations...", self.config.main.required_confirmations);
@@ -157,23 +215,12 @@ impl<T: Transport + Clone> Future for DeploySide<T> {
             let next_state = match self.state {
                 DeployState::Deployed { ref contract } => return Ok(contract.clone().into()),
                 DeployState::NotDeployed => {
-                    let data = contracts::side::constructor(
-                        self.config.side.contract.bin.clone().0,
-                        self.config.authorities.required_signatures,
-                        self.config.authorities.accounts.clone(),
-                        self.config.estimated_gas_cost_of_withdraw,
-                    );
-
-                    let tx_request = TransactionRequest {
-                        from: self.config.address,
-                        to: None,
-                        gas: Some(self.config.txs.side_deploy.gas.into()),
-                        gas_price: Some(self.config.txs.side_deploy.gas_price.into()),
-                        value: None,
-                        data: Some(data.clone().into()),
-                        nonce: None,
-                        condition: None,
-                    };
+                    let tx_request = side_deploy_request(&self.config);
+                    let data = tx_request
+                        .data
+                        .clone()
+                        .expect("deploy request always has data; qed")
+                        .0;
 
                     let future = SendTransactionWithReceipt::new(SendTransactionWithReceiptOptions {
                         transport: self.side_transport.clone(),
@@ -181,6 +228,8 @@ impl<T: Transport + Clone> Future for DeploySide<T> {
                         poll_interval: self.config.side.poll_interval,
                         confirmations: self.config.side.required_confirmations,
                         transaction: tx_request,
+                        max_retries: self.config.txs.side_deploy.max_retries,
+                        retry_delay: self.config.txs.side_deploy.retry_delay,
                     });
 
                     info!("sending SideBridge contract deployment transaction and waiting for {} confirmations...", self.config.side.required_confirmations);
@@ -18,6 +18,7 @@
 
 use std::io;
 use tokio_timer::{TimeoutError, TimerError};
+use web3::types::H256;
 use {ethabi, rustc_hex, toml, web3};
 
 error_chain! {
@@ -44,6 +45,18 @@ error_chain! {
             description("File not found"),
             display("File {} not found", filename),
         }
+        EmergencyStop(filename: String, contents: String) {
+            description("Emergency stop file present"),
+            display("Refusing to start: emergency stop file {} is present. investigate and delete it manually to restart the bridge.\n{}", filename, contents),
+        }
+        WrongChain(chain: String, expected: H256, actual: H256) {
+            description("Node is connected to the wrong chain"),
+            display("{} node is connected to the wrong chain. expected genesis block hash {:?} but got {:?}", chain, expected, actual),
+        }
+        WrongChainId(chain: String, expected: u64, actual: u64) {
+            description("Node is connected to the wrong chain"),
+            display("{} node is connected to the wrong chain. expected chain id {} but got {}", chain, expected, actual),
+        }
         // workaround for lack of web3:Error Display and Error implementations
         Web3(err: web3::Error) {
             description("web3 error"),
@@ -0,0 +1,144 @@
+// Copyright 2017 Parity Technologies (UK) Ltd.
+// This file is part of Parity-Bridge.
+
+// Parity-Bridge is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity-Bridge is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity-Bridge.  If not, see <http://www.gnu.org/licenses/>.
+
+//! splitting a fee between multiple recipients
+
+use error::Error;
+use ethereum_types::{Address, U256};
+
+/// basis points that the allocations of a `FeeDistribution` must sum up to
+pub const TOTAL_BASIS_POINTS: u32 = 10000;
+
+/// how a fee is split between recipients.
+/// each recipient gets an allocation in basis points.
+/// construct it with `FeeDistribution::new` which validates the allocations.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FeeDistribution {
+    recipients: Vec<(Address, u32)>,
+}
+
+impl FeeDistribution {
+    /// fails if there are no recipients or the allocations
+    /// don't sum up to `TOTAL_BASIS_POINTS`
+    pub fn new(recipients: Vec<(Address, u32)>) -> Result<Self, Error> {
+        if recipients.is_empty() {
+            bail!("fee distribution must have at least one recipient");
+        }
+        let total: u64 = recipients.iter().map(|&(_, bps)| bps as u64).sum();
+        if total != TOTAL_BASIS_POINTS as u64 {
+            bail!(
+                "fee distribution allocations must sum up to {} basis points but sum up to {}",
+                TOTAL_BASIS_POINTS,
+                total
+            );
+        }
+        Ok(Self { recipients })
+    }
+
+    /// recipients and their allocations in basis points
+    pub fn recipients(&self) -> &[(Address, u32)] {
+        &self.recipients
+    }
+
+    /// splits `total_fee` according to the allocations.
+    /// rounding dust goes to the first recipient so the parts always
+    /// sum up to `total_fee`.
+    pub fn split(&self, total_fee: U256) -> Vec<(Address, U256)> {
+        let mut parts = self.recipients
+            .iter()
+            .map(|&(address, bps)| {
+                // divide first so this can't overflow for any `total_fee`.
+                // equal to `total_fee * bps / TOTAL_BASIS_POINTS` rounded down
+                let total = U256::from(TOTAL_BASIS_POINTS);
+                let bps = U256::from(bps);
+                let value = total_fee / total * bps + total_fee % total * bps / total;
+                (address, value)
+            })
+            .collect::<Vec<_>>();
+
+        let distributed = parts
+            .iter()
+            .fold(U256::zero(), |sum, &(_, value)| sum + value);
+        if let Some(first) = parts.first_mut() {
+            first.1 = first.1 + (total_fee - distributed);
+        }
+
+        parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::TestResult;
+
+    fn address(n: u8) -> Address {
+        let mut address = Address::zero();
+        address.0[19] = n;
+        address
+    }
+
+    #[test]
+    fn test_fee_distribution_new_rejects_wrong_total() {
+        assert!(FeeDistribution::new(vec![]).is_err());
+        assert!(FeeDistribution::new(vec![(address(1), 5000), (address(2), 4999)]).is_err());
+        assert!(FeeDistribution::new(vec![(address(1), 5000), (address(2), 5000)]).is_ok());
+    }
+
+    #[test]
+    fn test_fee_distribution_split_dust_goes_to_first() {
+        let distribution =
+            FeeDistribution::new(vec![(address(1), 3333), (address(2), 3333), (address(3), 3334)])
+                .unwrap();
+
+        let expected: Vec<(Address, U256)> = vec![
+            (address(1), 34.into()),
+            (address(2), 33.into()),
+            (address(3), 33.into()),
+        ];
+        assert_eq!(distribution.split(100.into()), expected);
+    }
+
+    #[test]
+    fn test_fee_distribution_split_does_not_overflow() {
+        let distribution =
+            FeeDistribution::new(vec![(address(1), 2500), (address(2), 7500)]).unwrap();
+
+        let parts = distribution.split(U256::max_value());
+        assert_eq!(parts[1].1, U256::max_value() / 4.into() * 3.into() + 2.into());
+        assert_eq!(parts[0].1 + parts[1].1, U256::max_value());
+    }
+
+    quickcheck! {
+        fn quickcheck_fee_distribution_split_sums_to_total(total_fee: u64, first_bps: u16) -> TestResult {
+            let first_bps = first_bps as u32;
+            if first_bps > TOTAL_BASIS_POINTS {
+                return TestResult::discard();
+            }
+
+            let distribution = FeeDistribution::new(vec![
+                (address(1), first_bps),
+                (address(2), TOTAL_BASIS_POINTS - first_bps),
+            ]).unwrap();
+
+            let parts = distribution.split(total_fee.into());
+            let sum = parts.iter().fold(U256::zero(), |sum, &(_, value)| sum + value);
+            assert_eq!(sum, U256::from(total_fee));
+
+            TestResult::passed()
+        }
+    }
+}
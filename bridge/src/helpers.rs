@@ -16,17 +16,18 @@
 
 //! various helper functions
 
-use error::{self, ResultExt};
+use error::{self, ErrorKind, ResultExt};
 use ethabi::{self, RawLog, FunctionOutputDecoder};
 use futures::future::FromErr;
 use futures::{Async, Future, Poll, Stream};
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serializer};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tiny_keccak;
 use tokio_timer::{Timeout, Timer};
 use web3::api::Namespace;
 use web3::helpers::CallFuture;
-use web3::types::{Address, Bytes, CallRequest, H256, TransactionRequest, U256};
+use web3::types::{Address, BlockId, BlockNumber, Bytes, CallRequest, H256, TransactionRequest, U256};
 use web3::{self, Transport};
 
 /// attempts to convert a raw `web3_log` into the ethabi log type of a specific `event`
@@ -122,6 +123,160 @@ impl<T: Transport> Future for AsyncTransaction<T> {
     }
 }
 
+/// returns a `Future` that resolves if the genesis block of the chain
+/// `transport` is connected to has hash `expected`.
+/// fails with `ErrorKind::WrongChain` otherwise.
+/// `chain` names the chain in the error (`main` or `side`).
+pub fn check_genesis_hash<T: Transport>(
+    transport: &T,
+    chain: &'static str,
+    expected: H256,
+    timeout: Duration,
+) -> impl Future<Item = (), Error = error::Error> {
+    let future = web3::api::Eth::new(transport)
+        .block(BlockId::Number(BlockNumber::Number(0)))
+        .from_err();
+    Timer::default()
+        .timeout(future, timeout)
+        .and_then(move |block| {
+            let actual = block
+                .and_then(|block| block.hash)
+                .ok_or_else(|| format!("{} node returned no genesis block", chain))?;
+            if actual != expected {
+                return Err(ErrorKind::WrongChain(chain.into(), expected, actual).into());
+            }
+            Ok(())
+        })
+}
+
+/// returns a `Future` that resolves if `eth_chainId` of the node
+/// `transport` is connected to returns `expected`.
+/// fails with `ErrorKind::WrongChainId` otherwise.
+/// `chain` names the chain in the error (`main` or `side`).
+pub fn check_chain_id<T: Transport>(
+    transport: &T,
+    chain: &'static str,
+    expected: u64,
+    timeout: Duration,
+) -> impl Future<Item = (), Error = error::Error> {
+    let future: CallFuture<U256, T::Out> = CallFuture::new(transport.execute("eth_chainId", vec![]));
+    Timer::default()
+        .timeout(future.from_err(), timeout)
+        .and_then(move |actual| {
+            if actual != U256::from(expected) {
+                let actual = if actual > U256::from(u64::max_value()) {
+                    u64::max_value()
+                } else {
+                    actual.low_u64()
+                };
+                return Err(ErrorKind::WrongChainId(chain.into(), expected, actual).into());
+            }
+            Ok(())
+        })
+}
+
+/// returns a `Future` that fetches `net_version` from both nodes
+/// and fails with an error naming the chains whose node didn't respond
+/// properly within its timeout
+pub fn health_check<T: Transport>(
+    main_transport: &T,
+    main_timeout: Duration,
+    side_transport: &T,
+    side_timeout: Duration,
+) -> impl Future<Item = (), Error = error::Error> {
+    let main = Timer::default()
+        .timeout(web3::api::Net::new(main_transport).version().from_err(), main_timeout)
+        .then(Ok::<_, error::Error>);
+    let side = Timer::default()
+        .timeout(web3::api::Net::new(side_transport).version().from_err(), side_timeout)
+        .then(Ok::<_, error::Error>);
+    main.join(side).and_then(|(main, side)| {
+        let failures = [("main", main), ("side", side)]
+            .iter()
+            .filter_map(|&(chain, ref result)| {
+                result
+                    .as_ref()
+                    .err()
+                    .map(|err| format!("{} node: {}", chain, err))
+            })
+            .collect::<Vec<_>>();
+        if !failures.is_empty() {
+            bail!("health check failed. {}", failures.join(". "));
+        }
+        Ok(())
+    })
+}
+
+/// nearest rank `percent`th percentile of `durations` which must not be empty
+pub fn percentile(durations: &mut [Duration], percent: usize) -> Duration {
+    durations.sort();
+    let rank = (durations.len() * percent + 99) / 100;
+    durations[rank.saturating_sub(1)]
+}
+
+/// number of round trips `calibrate_timeout` measures
+pub const TIMEOUT_CALIBRATION_SAMPLES: usize = 5;
+
+/// returns a `Future` that measures `samples` sequential `eth_blockNumber`
+/// round trips to the node `transport` is connected to
+/// and resolves with their `calibrated_timeout`.
+/// round trips that time out count as taking `configured_timeout`.
+pub fn calibrate_timeout<T: Transport>(
+    transport: &T,
+    configured_timeout: Duration,
+    samples: usize,
+) -> impl Future<Item = Duration, Error = error::Error> {
+    let transport = transport.clone();
+    ::futures::stream::iter_ok::<_, error::Error>(0..samples)
+        .and_then(move |_| {
+            let start = Instant::now();
+            Timer::default()
+                .timeout(
+                    web3::api::Eth::new(&transport).block_number().from_err(),
+                    configured_timeout,
+                )
+                .then(move |result| match result {
+                    Ok(_) => Ok(start.elapsed()),
+                    Err(error::Error(ErrorKind::TimedOut, _)) => Ok(configured_timeout),
+                    Err(err) => Err(err),
+                })
+        })
+        .collect()
+        .map(move |mut round_trips| calibrated_timeout(configured_timeout, &mut round_trips))
+}
+
+/// the larger of `configured_timeout` and 3 times the 95th percentile of `round_trips`
+pub fn calibrated_timeout(configured_timeout: Duration, round_trips: &mut [Duration]) -> Duration {
+    if round_trips.is_empty() {
+        return configured_timeout;
+    }
+    let p95 = percentile(round_trips, 95);
+    ::std::cmp::max(configured_timeout, p95 * 3)
+}
+
+/// formats `address` with the mixed case checksum of EIP-55.
+/// example: `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`
+pub fn to_checksum_address(address: &Address) -> String {
+    let hex = format!("{:x}", address);
+    let hash = tiny_keccak::keccak256(hex.as_bytes());
+    let checksummed = hex.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    format!("0x{}", checksummed)
+}
+
 /// the toml crate parses integer literals as `i64`.
 /// certain config options (example: `max_total_home_contract_balance`)
 /// frequently don't fit into `i64`.
@@ -142,6 +297,29 @@ where
     serializer.serialize_str(&format!("{}", value))
 }
 
+pub fn deserialize_option_u256<'de, D>(deserializer: D) -> Result<Option<U256>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<&str> = Deserialize::deserialize(deserializer)?;
+    match s {
+        Some(s) => U256::from_dec_str(s)
+            .map(Some)
+            .map_err(|_| D::Error::custom("failed to parse U256 from dec str")),
+        None => Ok(None),
+    }
+}
+
+pub fn serialize_option_u256<S>(value: &Option<U256>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match *value {
+        Some(ref value) => serialize_u256(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
 /// extends the `Stream` trait by the `last` function
 pub trait StreamExt<I> {
     /// if you're interested only in the last item in a stream
@@ -197,8 +375,184 @@ where
 mod tests {
     use super::*;
     use futures;
+    use test::block_json;
     use tokio_core::reactor::Core;
 
+    #[test]
+    fn test_check_genesis_hash() {
+        let genesis = block_json(
+            "0x0",
+            "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "0x0000000000000000000000000000000000000000000000000000000000000000",
+            json!([]),
+        );
+
+        let transport = mock_transport!(
+            "eth_getBlockByNumber" =>
+                req => json!(["0x0", false]),
+                res => genesis.clone();
+            "eth_getBlockByNumber" =>
+                req => json!(["0x0", false]),
+                res => genesis.clone();
+        );
+
+        let mut event_loop = Core::new().unwrap();
+
+        let expected: H256 = "0x0000000000000000000000000000000000000000000000000000000000000001".into();
+        let other: H256 = "0x0000000000000000000000000000000000000000000000000000000000000002".into();
+        event_loop
+            .run(check_genesis_hash(&transport, "main", expected, Duration::from_secs(1)))
+            .unwrap();
+        match *event_loop
+            .run(check_genesis_hash(&transport, "main", other, Duration::from_secs(1)))
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::WrongChain(ref chain, wrong_expected, actual) => {
+                assert_eq!(chain, "main");
+                assert_eq!(wrong_expected, other);
+                assert_eq!(actual, expected);
+            }
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
+    #[test]
+    fn test_check_chain_id() {
+        let transport = mock_transport!(
+            "eth_chainId" =>
+                req => json!([]),
+                res => json!("0x2a");
+            "eth_chainId" =>
+                req => json!([]),
+                res => json!("0x2a");
+        );
+
+        let mut event_loop = Core::new().unwrap();
+        event_loop
+            .run(check_chain_id(&transport, "side", 42, Duration::from_secs(1)))
+            .unwrap();
+        match *event_loop
+            .run(check_chain_id(&transport, "side", 1, Duration::from_secs(1)))
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::WrongChainId(ref chain, expected, actual) => {
+                assert_eq!(chain, "side");
+                assert_eq!(expected, 1);
+                assert_eq!(actual, 42);
+            }
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mut durations = (1..11).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&mut durations, 50), Duration::from_millis(5));
+        assert_eq!(percentile(&mut durations, 95), Duration::from_millis(10));
+        assert_eq!(percentile(&mut durations, 99), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_calibrate_timeout_keeps_larger_configured_timeout() {
+        let transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x1");
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x1");
+        );
+
+        let mut event_loop = Core::new().unwrap();
+        let timeout = event_loop
+            .run(calibrate_timeout(&transport, Duration::from_secs(60), 2))
+            .unwrap();
+        // the mock transport answers immediately
+        assert_eq!(timeout, Duration::from_secs(60));
+
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
+    #[test]
+    fn test_calibrated_timeout() {
+        let mut round_trips = vec![
+            Duration::from_millis(100),
+            Duration::from_millis(120),
+            Duration::from_millis(110),
+            Duration::from_millis(900),
+            Duration::from_millis(130),
+        ];
+        // p95 of 900ms times 3 exceeds the configured timeout
+        assert_eq!(
+            calibrated_timeout(Duration::from_secs(1), &mut round_trips),
+            Duration::from_millis(2700)
+        );
+        assert_eq!(
+            calibrated_timeout(Duration::from_secs(5), &mut round_trips),
+            Duration::from_secs(5)
+        );
+        // a timed out round trip counts as the configured timeout
+        let mut round_trips = vec![Duration::from_millis(10), Duration::from_secs(1)];
+        assert_eq!(
+            calibrated_timeout(Duration::from_secs(1), &mut round_trips),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            calibrated_timeout(Duration::from_secs(1), &mut []),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_health_check_names_failing_chain() {
+        let main_transport = mock_transport!(
+            "net_version" =>
+                req => json!([]),
+                res => json!("17");
+        );
+        // a response that isn't a string can't be decoded as version
+        let side_transport = mock_transport!(
+            "net_version" =>
+                req => json!([]),
+                res => json!(null);
+        );
+
+        let mut event_loop = Core::new().unwrap();
+        let message = event_loop
+            .run(health_check(
+                &main_transport,
+                Duration::from_secs(1),
+                &side_transport,
+                Duration::from_secs(1),
+            ))
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("side node"));
+        assert!(!message.contains("main node"));
+
+        assert_eq!(main_transport.actual_requests(), main_transport.expected_requests());
+        assert_eq!(side_transport.actual_requests(), side_transport.expected_requests());
+    }
+
+    #[test]
+    fn test_to_checksum_address() {
+        // test vectors from EIP-55
+        for expected in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ].iter()
+        {
+            let address: Address = expected[2..].to_lowercase().parse().unwrap();
+            assert_eq!(to_checksum_address(&address), *expected);
+        }
+    }
+
     #[test]
     fn test_stream_ext_last_empty() {
         let stream = futures::stream::empty::<(), ()>();
@@ -66,6 +66,7 @@ extern crate ethabi;
 extern crate ethereum_types;
 #[macro_use]
 extern crate futures;
+extern crate jsonrpc_core;
 #[macro_use]
 extern crate log;
 #[macro_use]
@@ -82,6 +83,7 @@ extern crate serde_json;
 extern crate bridge_contracts as contracts;
 extern crate tiny_keccak;
 extern crate tokio_core;
+extern crate tokio_io;
 extern crate tokio_timer;
 extern crate toml;
 extern crate web3;
@@ -93,6 +95,7 @@ mod macros;
 #[macro_use]
 mod test;
 
+pub mod abi_validation;
 mod block_number_stream;
 mod bridge;
 pub use bridge::Bridge;
@@ -100,6 +103,7 @@ pub mod config;
 pub mod database;
 pub mod deploy;
 pub mod error;
+pub mod fee_distribution;
 mod ordered_stream;
 pub use ordered_stream::OrderedStream;
 pub mod helpers;
@@ -111,11 +115,12 @@ mod relay_stream;
 pub use relay_stream::RelayStream;
 mod send_tx_with_receipt;
 mod side_contract;
-pub use side_contract::SideContract;
+pub use side_contract::{RelayLink, RelayStatus, SideContract};
 mod side_to_main_sign;
 pub use side_to_main_sign::SideToMainSign;
 mod side_to_main_signatures;
 pub use side_to_main_signatures::SideToMainSignatures;
+pub mod status;
 
 mod log_stream;
 pub use log_stream::{LogStream, LogStreamOptions};
@@ -123,11 +128,13 @@ pub use log_stream::{LogStream, LogStreamOptions};
 mod signature;
 pub use signature::Signature;
 
+pub mod transport;
+
 mod message_to_main;
 pub use message_to_main::{MessageToMain, MESSAGE_LENGTH};
 
 #[cfg(test)]
-extern crate jsonrpc_core;
+extern crate tempdir;
 
 #[cfg(test)]
 pub use test::MockTransport;
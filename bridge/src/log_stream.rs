@@ -26,6 +26,56 @@ use web3::helpers::CallFuture;
 use web3::types::{Address, FilterBuilder, H256, Log};
 use web3::Transport;
 use block_number_stream::{BlockNumberStream, BlockNumberStreamOptions};
+use config::{duration_as_millis, AdaptivePolling, PollBackoff};
+
+/// number of consecutive polls without logs after which
+/// adaptive polling doubles the poll interval
+const IDLE_POLLS_BEFORE_SLOWING_DOWN: u32 = 3;
+
+/// computes the poll interval to use after a poll that yielded `logs_count` logs.
+/// `idle_polls` is the number of consecutive polls without logs
+/// including the current one.
+fn next_poll_interval(
+    adaptive_polling: &AdaptivePolling,
+    current: Duration,
+    logs_count: u64,
+    idle_polls: u32,
+) -> Duration {
+    if logs_count > adaptive_polling.busy_threshold {
+        ::std::cmp::max(current / 2, adaptive_polling.min_poll_interval)
+    } else if idle_polls >= IDLE_POLLS_BEFORE_SLOWING_DOWN {
+        ::std::cmp::min(current * 2, adaptive_polling.max_poll_interval)
+    } else {
+        current
+    }
+}
+
+/// computes the poll interval to use after a poll that yielded `logs_count` logs
+/// if polling backs off
+fn next_backoff_poll_interval(
+    poll_backoff: &PollBackoff,
+    base: Duration,
+    current: Duration,
+    logs_count: u64,
+) -> Duration {
+    if logs_count > 0 {
+        return base;
+    }
+    let current_millis = duration_as_millis(current);
+    let next = Duration::from_millis((current_millis as f64 * poll_backoff.multiplier) as u64);
+    ::std::cmp::min(next, poll_backoff.max_interval)
+}
+
+/// if fetching the inclusive block range `[from, to]` returned more than
+/// `page_size` logs returns the last block of the first half of the range
+/// which should be fetched instead
+pub fn split_block_range(from: u64, to: u64, logs_count: u64, page_size: u64) -> Option<u64> {
+    if logs_count > page_size && from < to {
+        Some(from + (to - from) / 2)
+    } else {
+        None
+    }
+}
 
 fn ethabi_topic_to_web3(topic: &ethabi::Topic<ethabi::Hash>) -> Option<Vec<H256>> {
     match topic {
@@ -54,6 +104,11 @@ pub struct LogStreamOptions<T> {
     pub transport: T,
     pub contract_address: Address,
     pub after: u64,
+    /// if a single `eth_getLogs` call returns more logs than this
+    /// the block range is split in half and fetched again
+    pub page_size: u64,
+    pub adaptive_polling: Option<AdaptivePolling>,
+    pub poll_backoff: Option<PollBackoff>,
 }
 
 /// Contains all logs matching `LogStream` filter in inclusive block range `[from, to]`.
@@ -79,11 +134,21 @@ enum State<T: Transport> {
 /// `Stream` that repeatedly polls logs matching `filter_builder` from `contract_address`
 /// with adjustable `poll_interval` and `request_timeout`.
 /// yields new logs that are `confirmations` blocks deep.
+/// block ranges with more than `page_size` logs are fetched in smaller chunks.
 pub struct LogStream<T: Transport> {
     block_number_stream: BlockNumberStream<T>,
     request_timeout: Duration,
     transport: T,
     last_checked_block: u64,
+    /// last confirmed block number. logs are fetched up to it.
+    last_confirmed_block: u64,
+    page_size: u64,
+    adaptive_polling: Option<AdaptivePolling>,
+    poll_backoff: Option<PollBackoff>,
+    /// `poll_interval` the stream was created with
+    base_poll_interval: Duration,
+    poll_interval: Duration,
+    idle_polls: u32,
     timer: Timer,
     state: State<T>,
     filter_builder: FilterBuilder,
@@ -111,12 +176,57 @@ impl<T: Transport> LogStream<T> {
             request_timeout: options.request_timeout,
             transport: options.transport,
             last_checked_block: options.after,
+            last_confirmed_block: options.after,
+            page_size: options.page_size,
+            adaptive_polling: options.adaptive_polling,
+            poll_backoff: options.poll_backoff,
+            base_poll_interval: options.poll_interval,
+            poll_interval: options.poll_interval,
+            idle_polls: 0,
             timer,
             state: State::AwaitBlockNumber,
             filter_builder,
             topic,
         }
     }
+
+    /// adjusts the poll interval of the underlying `BlockNumberStream`
+    /// if adaptive polling or poll backoff is enabled
+    fn adapt_poll_interval(&mut self, logs_count: u64) {
+        let next = if let Some(ref adaptive_polling) = self.adaptive_polling {
+            self.idle_polls = if logs_count == 0 { self.idle_polls + 1 } else { 0 };
+            next_poll_interval(adaptive_polling, self.poll_interval, logs_count, self.idle_polls)
+        } else if let Some(ref poll_backoff) = self.poll_backoff {
+            next_backoff_poll_interval(poll_backoff, self.base_poll_interval, self.poll_interval, logs_count)
+        } else {
+            return;
+        };
+
+        if next != self.poll_interval {
+            info!("LogStream (topic: {:?}): poll interval is now {:?}", self.topic, next);
+            self.idle_polls = 0;
+            self.poll_interval = next;
+            self.block_number_stream.set_poll_interval(next);
+        }
+    }
+
+    /// returns the state in which logs in inclusive block range `[from, to]` are fetched
+    fn fetch_logs(&self, from: u64, to: u64) -> State<T> {
+        let filter = self.filter_builder
+            .clone()
+            .from_block(from.into())
+            .to_block(to.into())
+            .build();
+        let future = web3::api::Eth::new(&self.transport).logs(filter);
+
+        info!("LogStream: fetching logs in blocks {} to {}", from, to);
+
+        State::AwaitLogs {
+            from,
+            to,
+            future: self.timer.timeout(future.from_err(), self.request_timeout),
+        }
+    }
 }
 
 impl<T: Transport> Stream for LogStream<T> {
@@ -134,26 +244,9 @@ impl<T: Transport> Stream for LogStream<T> {
                     );
                     info!("LogStream: fetched confirmed block number {}", last_block);
 
+                    self.last_confirmed_block = last_block;
                     let from = self.last_checked_block + 1;
-                    let filter = self.filter_builder
-                        .clone()
-                        .from_block(from.into())
-                        .to_block(last_block.into())
-                        .build();
-                    let future = web3::api::Eth::new(&self.transport).logs(filter);
-
-                    info!(
-                        "LogStream: fetching logs in blocks {} to {}",
-                        from, last_block
-                    );
-
-                    let next_state = State::AwaitLogs {
-                        from: from,
-                        to: last_block,
-                        future: self.timer.timeout(future.from_err(), self.request_timeout),
-                    };
-
-                    (next_state, None)
+                    (self.fetch_logs(from, last_block), None)
                 }
                 State::AwaitLogs {
                     ref mut future,
@@ -172,10 +265,29 @@ impl<T: Transport> Stream for LogStream<T> {
                         from,
                         to
                     );
-                    let log_range_to_yield = LogsInBlockRange { from, to, logs };
 
-                    self.last_checked_block = to;
-                    (State::AwaitBlockNumber, Some(log_range_to_yield))
+                    if let Some(middle) = split_block_range(from, to, logs.len() as u64, self.page_size) {
+                        info!(
+                            "LogStream (topic: {:?}): more than {} logs. splitting block range and fetching blocks {} to {} first",
+                            self.topic,
+                            self.page_size,
+                            from,
+                            middle
+                        );
+                        (self.fetch_logs(from, middle), None)
+                    } else {
+                        self.adapt_poll_interval(logs.len() as u64);
+                        let log_range_to_yield = LogsInBlockRange { from, to, logs };
+
+                        self.last_checked_block = to;
+                        let next_state = if to < self.last_confirmed_block {
+                            // the rest of the block range split off earlier
+                            self.fetch_logs(to + 1, self.last_confirmed_block)
+                        } else {
+                            State::AwaitBlockNumber
+                        };
+                        (next_state, Some(log_range_to_yield))
+                    }
                 }
             };
 
@@ -233,6 +345,9 @@ mod tests {
             contract_address: "0000000000000000000000000000000000000001".into(),
             after: 3,
             filter: contracts::main::events::deposit::filter(),
+            page_size: 10000,
+            adaptive_polling: None,
+            poll_backoff: None,
         });
 
         let mut event_loop = Core::new().unwrap();
@@ -288,6 +403,9 @@ mod tests {
             contract_address: "0000000000000000000000000000000000000001".into(),
             after: 3,
             filter: contracts::main::events::deposit::filter(),
+            page_size: 10000,
+            adaptive_polling: None,
+            poll_backoff: None,
         });
 
         let mut event_loop = Core::new().unwrap();
@@ -314,4 +432,117 @@ mod tests {
             ]);
         assert_eq!(transport.actual_requests(), transport.expected_requests());
     }
+
+    #[test]
+    fn test_log_stream_splits_block_range_with_too_many_logs() {
+        let deposit_topic = contracts::main::events::deposit::filter().topic0;
+        let log = json!({
+            "address": "0x0000000000000000000000000000000000000cc1",
+            "topics": [deposit_topic],
+            "data": "0x000000000000000000000000aff3454fce5edbc8cca8697c15331677e6ebcccc00000000000000000000000000000000000000000000000000000000000000f0",
+            "type": "",
+            "transactionHash": "0x884edad9ce6fa2440d8a54cc123490eb96d2768479d49ff9c7366125a9424364"
+        });
+
+        let transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x10");
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000001",
+                    "fromBlock": "0x4",
+                    "toBlock": "0x6",
+                    "topics": [deposit_topic],
+                }]),
+                res => json!([log, log]);
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000001",
+                    "fromBlock": "0x4",
+                    "toBlock": "0x5",
+                    "topics": [deposit_topic],
+                }]),
+                res => json!([log]);
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000001",
+                    "fromBlock": "0x6",
+                    "toBlock": "0x6",
+                    "topics": [deposit_topic],
+                }]),
+                res => json!([log]);
+        );
+
+        let log_stream = LogStream::new(LogStreamOptions {
+            request_timeout: Duration::from_secs(1),
+            poll_interval: Duration::from_secs(1),
+            confirmations: 10,
+            transport: transport.clone(),
+            contract_address: "0000000000000000000000000000000000000001".into(),
+            after: 3,
+            filter: contracts::main::events::deposit::filter(),
+            page_size: 1,
+            adaptive_polling: None,
+            poll_backoff: None,
+        });
+
+        let mut event_loop = Core::new().unwrap();
+        let log_ranges = event_loop.run(log_stream.take(2).collect()).unwrap();
+
+        assert_eq!(
+            log_ranges
+                .iter()
+                .map(|range| (range.from, range.to, range.logs.len()))
+                .collect::<Vec<_>>(),
+            vec![(4, 5, 1), (6, 6, 1)]
+        );
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
+    #[test]
+    fn test_next_poll_interval() {
+        let adaptive_polling = AdaptivePolling {
+            busy_threshold: 5,
+            min_poll_interval: Duration::from_millis(300),
+            max_poll_interval: Duration::from_secs(2),
+        };
+        let second = Duration::from_secs(1);
+
+        assert_eq!(next_poll_interval(&adaptive_polling, second, 6, 0), Duration::from_millis(500));
+        assert_eq!(
+            next_poll_interval(&adaptive_polling, Duration::from_millis(500), 6, 0),
+            Duration::from_millis(300)
+        );
+        assert_eq!(next_poll_interval(&adaptive_polling, second, 5, 0), second);
+        assert_eq!(next_poll_interval(&adaptive_polling, second, 0, 2), second);
+        assert_eq!(next_poll_interval(&adaptive_polling, second, 0, 3), Duration::from_secs(2));
+        assert_eq!(
+            next_poll_interval(&adaptive_polling, Duration::from_secs(2), 0, 3),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn test_next_backoff_poll_interval() {
+        let poll_backoff = PollBackoff {
+            multiplier: 1.5,
+            max_interval: Duration::from_secs(2),
+        };
+        let second = Duration::from_secs(1);
+
+        assert_eq!(
+            next_backoff_poll_interval(&poll_backoff, second, second, 0),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            next_backoff_poll_interval(&poll_backoff, second, Duration::from_millis(1500), 0),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            next_backoff_poll_interval(&poll_backoff, second, Duration::from_secs(2), 0),
+            Duration::from_secs(2)
+        );
+        assert_eq!(next_backoff_poll_interval(&poll_backoff, second, Duration::from_secs(2), 1), second);
+    }
 }
@@ -13,11 +13,12 @@
 
 // You should have received a copy of the GNU General Public License
 // along with Parity-Bridge.  If not, see <http://www.gnu.org/licenses/>.
-use config::Config;
+use config::{AdaptivePolling, Config, PollBackoff};
 use contracts;
 use database::State;
 use ethabi::FunctionOutputDecoder;
 use ethereum_types::{Address, U256};
+use fee_distribution::FeeDistribution;
 use helpers::{AsyncCall, AsyncTransaction};
 use log_stream::{LogStream, LogStreamOptions};
 use message_to_main::MessageToMain;
@@ -29,27 +30,52 @@ use web3::Transport;
 #[derive(Clone)]
 pub struct MainContract<T> {
     pub transport: T,
+    /// used instead of `transport` to fetch logs if set
+    pub archive_transport: Option<T>,
     pub contract_address: Address,
     pub authority_address: Address,
     pub submit_collected_signatures_gas: U256,
     pub request_timeout: Duration,
     pub logs_poll_interval: Duration,
     pub required_log_confirmations: u32,
+    pub logs_page_size: u64,
+    pub logs_adaptive_polling: Option<AdaptivePolling>,
+    pub logs_poll_backoff: Option<PollBackoff>,
+    /// how the fee for relaying a withdraw is split
+    pub fee_distribution: Option<FeeDistribution>,
 }
 
 impl<T: Transport> MainContract<T> {
     pub fn new(transport: T, config: &Config, state: &State) -> Self {
         Self {
             transport,
+            archive_transport: None,
             contract_address: state.main_contract_address,
             authority_address: config.address,
             submit_collected_signatures_gas: config.estimated_gas_cost_of_withdraw,
             request_timeout: config.main.request_timeout,
             logs_poll_interval: config.main.poll_interval,
             required_log_confirmations: config.main.required_confirmations,
+            logs_page_size: config.main.log_filter_page_size,
+            logs_adaptive_polling: config.main.adaptive_polling.clone(),
+            logs_poll_backoff: config.main.poll_backoff.clone(),
+            fee_distribution: config.fee_distribution.clone(),
         }
     }
 
+    /// fetch logs through `archive_transport` which may be connected to
+    /// an archive node while `transport` is connected to a full node
+    pub fn with_archive_transport(mut self, archive_transport: T) -> Self {
+        self.archive_transport = Some(archive_transport);
+        self
+    }
+
+    fn logs_transport(&self) -> T {
+        self.archive_transport
+            .clone()
+            .unwrap_or_else(|| self.transport.clone())
+    }
+
     pub fn call<F: FunctionOutputDecoder>(&self, payload: Vec<u8>, output_decoder: F) -> AsyncCall<T, F> {
         AsyncCall::new(
             &self.transport,
@@ -72,9 +98,12 @@ impl<T: Transport> MainContract<T> {
             request_timeout: self.request_timeout,
             poll_interval: self.logs_poll_interval,
             confirmations: self.required_log_confirmations,
-            transport: self.transport.clone(),
+            transport: self.logs_transport(),
             contract_address: self.contract_address,
             after,
+            page_size: self.logs_page_size,
+            adaptive_polling: self.logs_adaptive_polling.clone(),
+            poll_backoff: self.logs_poll_backoff.clone(),
         })
     }
 
@@ -101,4 +130,13 @@ impl<T: Transport> MainContract<T> {
             payload,
         )
     }
+
+    /// what `mainContract.withdraw` pays the relaying authority for relaying `message`.
+    /// the contract computes it from its `estimatedGasCostOfWithdraw`
+    /// which is deployed from `estimated_gas_cost_of_withdraw`.
+    /// `None` if it overflows. the user initiating the withdraw chooses `main_gas_price`
+    pub fn relay_fee(&self, message: &MessageToMain) -> Option<U256> {
+        self.submit_collected_signatures_gas
+            .checked_mul(message.main_gas_price)
+    }
 }
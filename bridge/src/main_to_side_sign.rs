@@ -25,6 +25,8 @@ use web3::Transport;
 enum State<T: Transport> {
     AwaitAlreadySigned(AsyncCall<T, contracts::side::functions::has_authority_signed_main_to_side::Decoder>),
     AwaitTxSent(AsyncTransaction<T>),
+    /// the depositor is not in `depositor_whitelist`
+    Skipped,
 }
 
 /// `Future` that is responsible for calling `sideContract.deposit`
@@ -55,8 +57,13 @@ impl<T: Transport> MainToSideSign<T> {
         let recipient = log.recipient;
         let value = log.value;
 
-        let future = side.is_main_to_side_signed_on_side(recipient, value, main_tx_hash);
-        let state = State::AwaitAlreadySigned(future);
+        // `MainBridge` emits `Deposit` with `msg.sender` as the recipient
+        let state = if side.is_depositor_whitelisted(recipient) {
+            let future = side.is_main_to_side_signed_on_side(recipient, value, main_tx_hash);
+            State::AwaitAlreadySigned(future)
+        } else {
+            State::Skipped
+        };
 
         Self {
             main_tx_hash,
@@ -93,6 +100,13 @@ impl<T: Transport> Future for MainToSideSign<T> {
                         self.main_tx_hash,
                     ))
                 }
+                State::Skipped => {
+                    warn!(
+                        "{:?} - SKIPPED - depositor {:?} is not whitelisted",
+                        self.main_tx_hash, self.recipient
+                    );
+                    return Ok(Async::Ready(None));
+                }
                 State::AwaitTxSent(ref mut future) => {
                     let main_tx_hash = self.main_tx_hash;
                     let side_tx_hash = try_ready!(future.poll().chain_err(|| format!(
@@ -197,12 +211,17 @@ mod tests {
 
         let side_contract = SideContract {
             transport: transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
             sign_main_to_side_gas: 0xfd.into(),
             sign_main_to_side_gas_price: 0xa0.into(),
             sign_side_to_main_gas: 0.into(),
@@ -271,12 +290,17 @@ mod tests {
 
         let side_contract = SideContract {
             transport: transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
             sign_main_to_side_gas: 0xfd.into(),
             sign_main_to_side_gas_price: 0xa0.into(),
             sign_side_to_main_gas: 0.into(),
@@ -291,4 +315,66 @@ mod tests {
 
         assert_eq!(transport.actual_requests(), transport.expected_requests());
     }
+
+    #[test]
+    fn test_main_to_side_sign_relay_future_not_whitelisted() {
+        let topic = contracts::main::events::deposit::filter().topic0;
+
+        let log_data = ethabi::encode(&[
+            ethabi::Token::Address("aff3454fce5edbc8cca8697c15331677e6ebcccc".into()),
+            ethabi::Token::Uint(1000.into()),
+        ]);
+
+        let raw_log = Log {
+            address: "0000000000000000000000000000000000000001".into(),
+            topics: topic.into(),
+            data: Bytes(log_data),
+            transaction_hash: Some(
+                "0x884edad9ce6fa2440d8a54cc123490eb96d2768479d49ff9c7366125a9424364".into(),
+            ),
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+
+        // panics if any request is made
+        let transport = ::MockTransport {
+            actual_requests: Default::default(),
+            expected_requests: vec![],
+            mock_responses: vec![],
+        };
+
+        let side_contract = SideContract {
+            transport: transport.clone(),
+            archive_transport: None,
+            contract_address: "0000000000000000000000000000000000000dd1".into(),
+            authority_address: "0000000000000000000000000000000000000001".into(),
+            required_signatures: 1,
+            depositor_whitelist: ::std::rc::Rc::new(::std::cell::RefCell::new(Some(vec![
+                "0000000000000000000000000000000000000002".into(),
+            ]))),
+            request_timeout: ::std::time::Duration::from_millis(0),
+            logs_poll_interval: ::std::time::Duration::from_millis(0),
+            required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            sign_main_to_side_gas: 0xfd.into(),
+            sign_main_to_side_gas_price: 0xa0.into(),
+            sign_side_to_main_gas: 0.into(),
+            sign_side_to_main_gas_price: 0.into(),
+        };
+
+        let future = MainToSideSign::new(&raw_log, side_contract);
+
+        let mut event_loop = Core::new().unwrap();
+        let result = event_loop.run(future).unwrap();
+        assert_eq!(result, None);
+
+        assert!(transport.actual_requests().is_empty());
+    }
 }
@@ -22,22 +22,48 @@ use web3::{self, Transport};
 use web3::types::{TransactionRequest, TransactionReceipt, U256};
 use web3::helpers::CallFuture;
 use web3::api::Namespace;
-use error::{self, ResultExt};
+use error::{self, ErrorKind, ResultExt};
+
+/// whether sending a transaction failed with `err` before the node
+/// could answer: the request timed out or never got a response.
+/// the transaction may or may not have reached the node in that case.
+/// errors returned by the node itself (invalid code, out of gas,
+/// nonce too low) are not transient.
+fn is_transient_error(err: &error::Error) -> bool {
+    match *err.kind() {
+        ErrorKind::TimedOut => true,
+        ErrorKind::Web3(ref err) => match *err.kind() {
+            web3::ErrorKind::Unreachable
+            | web3::ErrorKind::Transport(_)
+            | web3::ErrorKind::Io(_) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
 
 mod inner {
     use std::time::Duration;
     use futures::future::FromErr;
     use futures::{Async, Future, Poll, Stream};
-    use tokio_timer::{Timeout, Timer};
+    use tokio_timer::{Sleep, Timeout, Timer};
     use web3::{self, Transport};
     use web3::api::Namespace;
     use web3::helpers::CallFuture;
-    use web3::types::{TransactionRequest, TransactionReceipt, H256};
+    use web3::types::{BlockNumber, TransactionRequest, TransactionReceipt, H256, U256};
     use error::{self, ResultExt};
     use block_number_stream::{BlockNumberStreamOptions, BlockNumberStream};
+    use super::is_transient_error;
 
     enum State<T: Transport> {
+        /// pending nonce of the sender. retries resend with the same nonce
+        /// so at most one of them can be mined
+        AwaitNonce(Timeout<FromErr<CallFuture<U256, T::Out>, error::Error>>),
         AwaitSendTransaction(Timeout<FromErr<CallFuture<H256, T::Out>, error::Error>>),
+        /// after a send failed without an answer from the node:
+        /// the pending nonce shows whether the transaction reached the node anyway
+        AwaitNonceCheck(Timeout<FromErr<CallFuture<U256, T::Out>, error::Error>>),
+        AwaitRetry(Sleep),
         AwaitBlockNumber(H256),
         AwaitTransactionReceipt {
             future: Timeout<FromErr<CallFuture<Option<TransactionReceipt>, T::Out>, error::Error>>,
@@ -53,6 +79,8 @@ mod inner {
         pub confirmations: u32,
         pub transaction: TransactionRequest,
         pub after: u64,
+        pub max_retries: u32,
+        pub retry_delay: Duration,
     }
 
     pub struct SendTransactionWithReceipt<T: Transport> {
@@ -61,6 +89,9 @@ mod inner {
         block_number_stream: BlockNumberStream<T>,
         request_timeout: Duration,
         timer: Timer,
+        transaction: TransactionRequest,
+        retries_left: u32,
+        retry_delay: Duration,
     }
 
     impl<T: Transport> SendTransactionWithReceipt<T> {
@@ -75,15 +106,24 @@ mod inner {
                 after: options.after,
             };
             let block_number_stream = BlockNumberStream::new(block_number_stream_options);
-            let future = web3::api::Eth::new(&options.transport).send_transaction(options.transaction);
-            let future = timer.timeout(future.from_err(), options.request_timeout);
+            let eth = web3::api::Eth::new(&options.transport);
+            let state = if options.max_retries > 0 && options.transaction.nonce.is_none() {
+                let future = eth.transaction_count(options.transaction.from, Some(BlockNumber::Pending));
+                State::AwaitNonce(timer.timeout(future.from_err(), options.request_timeout))
+            } else {
+                let future = eth.send_transaction(options.transaction.clone());
+                State::AwaitSendTransaction(timer.timeout(future.from_err(), options.request_timeout))
+            };
 
             SendTransactionWithReceipt {
                 transport: options.transport,
-                state: State::AwaitSendTransaction(future),
+                state,
                 block_number_stream,
                 request_timeout: options.request_timeout,
                 timer,
+                transaction: options.transaction,
+                retries_left: options.max_retries,
+                retry_delay: options.retry_delay,
             }
         }
     }
@@ -95,14 +135,62 @@ mod inner {
         fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
             loop {
                 let next_state = match self.state {
+                    State::AwaitNonce(ref mut future) => {
+                        let nonce = try_ready!(
+                            future
+                                .poll()
+                                .chain_err(|| "SendTransactionWithReceipt: fetching nonce failed")
+                        );
+                        self.transaction.nonce = Some(nonce);
+                        let future = web3::api::Eth::new(&self.transport).send_transaction(self.transaction.clone());
+                        State::AwaitSendTransaction(self.timer.timeout(future.from_err(), self.request_timeout))
+                    },
                     State::AwaitSendTransaction(ref mut future) => {
-                        let hash = try_ready!(
+                        match future.poll() {
+                            Ok(Async::NotReady) => return Ok(Async::NotReady),
+                            Ok(Async::Ready(hash)) => {
+                                info!("SendTransactionWithReceipt: sent transaction {}", hash);
+                                State::AwaitBlockNumber(hash)
+                            },
+                            Err(ref err) if self.retries_left > 0 && is_transient_error(err) => {
+                                self.retries_left -= 1;
+                                warn!(
+                                    "SendTransactionWithReceipt: sending transaction failed: {}. checking whether it reached the node",
+                                    err
+                                );
+                                let future = web3::api::Eth::new(&self.transport)
+                                    .transaction_count(self.transaction.from, Some(BlockNumber::Pending));
+                                State::AwaitNonceCheck(self.timer.timeout(future.from_err(), self.request_timeout))
+                            },
+                            Err(err) => {
+                                return Err(err).chain_err(|| "SendTransactionWithReceipt: sending transaction failed")
+                            },
+                        }
+                    },
+                    State::AwaitNonceCheck(ref mut future) => {
+                        let pending_nonce = try_ready!(
                             future
                                 .poll()
-                                .chain_err(|| "SendTransactionWithReceipt: sending transaction failed")
+                                .chain_err(|| "SendTransactionWithReceipt: fetching nonce failed")
+                        );
+                        let nonce = self.transaction.nonce
+                            .expect("retries are only enabled with a nonce set; qed");
+                        if pending_nonce > nonce {
+                            bail!(
+                                "SendTransactionWithReceipt: transaction with nonce {} from {:?} reached the node although sending it failed. not retrying to avoid sending it twice",
+                                nonce, self.transaction.from
+                            );
+                        }
+                        warn!(
+                            "SendTransactionWithReceipt: transaction didn't reach the node. retrying in {:?} ({} retries left)",
+                            self.retry_delay, self.retries_left
                         );
-                        info!("SendTransactionWithReceipt: sent transaction {}", hash);
-                        State::AwaitBlockNumber(hash)
+                        State::AwaitRetry(self.timer.sleep(self.retry_delay))
+                    },
+                    State::AwaitRetry(ref mut future) => {
+                        try_ready!(future.poll());
+                        let future = web3::api::Eth::new(&self.transport).send_transaction(self.transaction.clone());
+                        State::AwaitSendTransaction(self.timer.timeout(future.from_err(), self.request_timeout))
                     },
                     State::AwaitBlockNumber(transaction_hash) => {
                         let last_block = match try_ready!(
@@ -172,6 +260,9 @@ pub struct SendTransactionWithReceiptOptions<T> {
     pub poll_interval: Duration,
     pub confirmations: u32,
     pub transaction: TransactionRequest,
+    /// how often sending `transaction` is retried after a transient error
+    pub max_retries: u32,
+    pub retry_delay: Duration,
 }
 
 pub struct SendTransactionWithReceipt<T: Transport> {
@@ -180,6 +271,8 @@ pub struct SendTransactionWithReceipt<T: Transport> {
     transport: T,
     state: State<T>,
     confirmations: u32,
+    max_retries: u32,
+    retry_delay: Duration,
 }
 
 impl<T: Transport> SendTransactionWithReceipt<T> {
@@ -199,6 +292,8 @@ impl<T: Transport> SendTransactionWithReceipt<T> {
             transport: options.transport,
             state,
             confirmations: options.confirmations,
+            max_retries: options.max_retries,
+            retry_delay: options.retry_delay,
         }
     }
 }
@@ -231,6 +326,8 @@ impl<T: Transport> Future for SendTransactionWithReceipt<T> {
                         confirmations: self.confirmations,
                         transaction,
                         after: block_number.as_u64(),
+                        max_retries: self.max_retries,
+                        retry_delay: self.retry_delay,
                     };
 
                     let future = inner::SendTransactionWithReceipt::new(inner_options);
@@ -249,8 +346,133 @@ impl<T: Transport> Future for SendTransactionWithReceipt<T> {
 #[cfg(test)]
 mod tests {
     use tokio_core::reactor::Core;
+    use test::{receipt_json, MOCK_TRANSPORT_ERROR};
     use super::*;
 
+    #[test]
+    fn test_is_transient_error() {
+        assert!(is_transient_error(&ErrorKind::TimedOut.into()));
+        assert!(is_transient_error(
+            &web3::Error::from(web3::ErrorKind::Transport("connection refused".into())).into()
+        ));
+        assert!(!is_transient_error(
+            &web3::Error::from(web3::ErrorKind::InvalidResponse("nonce too low".into())).into()
+        ));
+        assert!(!is_transient_error(&"out of gas".into()));
+    }
+
+    fn retried_transaction() -> TransactionRequest {
+        TransactionRequest {
+            from: "0x006b5dda44dc2606f07ad86c9190fb54fd905f6d".into(),
+            to: None,
+            gas: Some(0xf4240.into()),
+            gas_price: Some(0.into()),
+            value: None,
+            data: Some(vec![0x60].into()),
+            nonce: None,
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn test_send_tx_with_receipt_retries_transaction_that_did_not_reach_node() {
+        let transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x1010");
+            "eth_getTransactionCount" =>
+                req => json!(["0x006b5dda44dc2606f07ad86c9190fb54fd905f6d", "pending"]),
+                res => json!("0x7");
+            "eth_sendTransaction" =>
+                req => json!([{
+                    "data": "0x60",
+                    "from": "0x006b5dda44dc2606f07ad86c9190fb54fd905f6d",
+                    "gas": "0xf4240",
+                    "gasPrice": "0x0",
+                    "nonce": "0x7"
+                }]),
+                res => json!(MOCK_TRANSPORT_ERROR);
+            "eth_getTransactionCount" =>
+                req => json!(["0x006b5dda44dc2606f07ad86c9190fb54fd905f6d", "pending"]),
+                res => json!("0x7");
+            // resent with the same nonce
+            "eth_sendTransaction" =>
+                req => json!([{
+                    "data": "0x60",
+                    "from": "0x006b5dda44dc2606f07ad86c9190fb54fd905f6d",
+                    "gas": "0xf4240",
+                    "gasPrice": "0x0",
+                    "nonce": "0x7"
+                }]),
+                res => json!("0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34");
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x1011");
+            "eth_getTransactionReceipt" =>
+                req => json!(["0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34"]),
+                res => receipt_json(
+                    Some(("0xe0bdcf35b14a292d2998308d9b3fdea93a8c3d9c0b6c824c633fb9b15f9c3919", "0x1011")),
+                    "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
+                    "0x4",
+                    "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
+                );
+        );
+
+        let send_transaction_with_receipt = SendTransactionWithReceipt::new(SendTransactionWithReceiptOptions {
+            transport: transport.clone(),
+            request_timeout: Duration::from_secs(1),
+            poll_interval: Duration::from_secs(0),
+            confirmations: 0,
+            transaction: retried_transaction(),
+            max_retries: 1,
+            retry_delay: Duration::from_secs(0),
+        });
+
+        let mut event_loop = Core::new().unwrap();
+        let receipt = event_loop.run(send_transaction_with_receipt).unwrap();
+        assert_eq!(receipt.block_number, Some(0x1011.into()));
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
+    #[test]
+    fn test_send_tx_with_receipt_does_not_resend_transaction_that_reached_node() {
+        let transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x1010");
+            "eth_getTransactionCount" =>
+                req => json!(["0x006b5dda44dc2606f07ad86c9190fb54fd905f6d", "pending"]),
+                res => json!("0x7");
+            "eth_sendTransaction" =>
+                req => json!([{
+                    "data": "0x60",
+                    "from": "0x006b5dda44dc2606f07ad86c9190fb54fd905f6d",
+                    "gas": "0xf4240",
+                    "gasPrice": "0x0",
+                    "nonce": "0x7"
+                }]),
+                res => json!(MOCK_TRANSPORT_ERROR);
+            // the transaction got into the pool although the response was lost
+            "eth_getTransactionCount" =>
+                req => json!(["0x006b5dda44dc2606f07ad86c9190fb54fd905f6d", "pending"]),
+                res => json!("0x8");
+        );
+
+        let send_transaction_with_receipt = SendTransactionWithReceipt::new(SendTransactionWithReceiptOptions {
+            transport: transport.clone(),
+            request_timeout: Duration::from_secs(1),
+            poll_interval: Duration::from_secs(0),
+            confirmations: 0,
+            transaction: retried_transaction(),
+            max_retries: 3,
+            retry_delay: Duration::from_secs(0),
+        });
+
+        let mut event_loop = Core::new().unwrap();
+        assert!(event_loop.run(send_transaction_with_receipt).is_err());
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+
     #[test]
     fn test_send_tx_with_receipt() {
         let transport = mock_transport!(
@@ -282,19 +504,12 @@ mod tests {
                 res => json!("0x1014");
             "eth_getTransactionReceipt" =>
                 req => json!(["0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34"]),
-                res => json!({
-                    "blockHash": null,
-                    "blockNumber": null,
-                    "contractAddress": "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
-                    "cumulativeGasUsed": "0x1c1999",
-                    "gasUsed": "0xcdb5d",
-                    "logs": [],
-                    "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
-                    "root": null,
-                    "status": "0x1",
-                    "transactionHash": "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
-                    "transactionIndex":"0x4"
-                });
+                res => receipt_json(
+                    None,
+                    "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
+                    "0x4",
+                    "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
+                );
             "eth_blockNumber" =>
                 req => json!([]),
                 res => json!("0x1014");
@@ -303,37 +518,23 @@ mod tests {
                 res => json!("0x1015");
             "eth_getTransactionReceipt" =>
                 req => json!(["0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34"]),
-                res => json!({
-                    "blockHash": "0xe0bdcf35b14a292d2998308d9b3fdea93a8c3d9c0b6c824c633fb9b15f9c3919",
-                    "blockNumber": "0x1015",
-                    "contractAddress": "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
-                    "cumulativeGasUsed": "0x1c1999",
-                    "gasUsed": "0xcdb5d",
-                    "logs": [],
-                    "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
-                    "root": null,
-                    "status": "0x1",
-                    "transactionHash": "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
-                    "transactionIndex":"0x4"
-                });
+                res => receipt_json(
+                    Some(("0xe0bdcf35b14a292d2998308d9b3fdea93a8c3d9c0b6c824c633fb9b15f9c3919", "0x1015")),
+                    "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
+                    "0x4",
+                    "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
+                );
             "eth_blockNumber" =>
                 req => json!([]),
                 res => json!("0x1017");
             "eth_getTransactionReceipt" =>
                 req => json!(["0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34"]),
-                res => json!({
-                    "blockHash": "0xe0bdcf35b14a292d2998308d9b3fdea93a8c3d9c0b6c824c633fb9b15f9c3919",
-                    "blockNumber": "0x1015",
-                    "contractAddress": "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
-                    "cumulativeGasUsed": "0x1c1999",
-                    "gasUsed": "0xcdb5d",
-                    "logs": [],
-                    "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
-                    "root": null,
-                    "status": "0x1",
-                    "transactionHash": "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
-                    "transactionIndex":"0x4"
-                });
+                res => receipt_json(
+                    Some(("0xe0bdcf35b14a292d2998308d9b3fdea93a8c3d9c0b6c824c633fb9b15f9c3919", "0x1015")),
+                    "0x36efc16910ea67a2425a1e75f7e39e3c6a94f5763c68a47258f552481e20cd34",
+                    "0x4",
+                    "0xb1ac3a5584519119419a8e56422d912c782d8e5b",
+                );
         );
 
         let send_transaction_with_receipt = SendTransactionWithReceipt::new(SendTransactionWithReceiptOptions {
@@ -350,7 +551,9 @@ mod tests {
                 data: Some(vec![0x60].into()),
                 nonce: None,
                 condition: None,
-            }
+            },
+            max_retries: 0,
+            retry_delay: Duration::from_secs(0),
         });
 
         let mut event_loop = Core::new().unwrap();
@@ -13,30 +13,67 @@
 
 // You should have received a copy of the GNU General Public License
 // along with Parity-Bridge.  If not, see <http://www.gnu.org/licenses/>.
-use config::Config;
+use config::{AdaptivePolling, Config, PollBackoff};
 use contracts;
 use database::State;
+use error::Error;
 use ethabi::FunctionOutputDecoder;
-use futures::future::{join_all, JoinAll};
-use helpers::{AsyncCall, AsyncTransaction};
-use log_stream::{LogStream, LogStreamOptions};
+use futures::future::{join_all, loop_fn, JoinAll, Loop};
+use futures::Future;
+use helpers::{self, to_checksum_address, AsyncCall, AsyncTransaction};
+use log_stream::{split_block_range, LogStream, LogStreamOptions};
 use message_to_main::MessageToMain;
 use signature::Signature;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::time::Duration;
-use web3::types::{Address, H256, U256};
+use tokio_timer::Timer;
+use web3;
+use web3::types::{Address, BlockNumber, FilterBuilder, H256, Log, U256};
 use web3::Transport;
 
+/// how far a deposit on `main` has been relayed to `side`
+#[derive(Debug, PartialEq, Clone)]
+pub enum RelayStatus {
+    /// `confirmations` authorities have signed the deposit on `side`
+    /// but not enough yet
+    Pending { confirmations: u32 },
+    /// enough authorities have signed the deposit and the value
+    /// has been credited to the recipient on `side`
+    Completed,
+}
+
+/// links a deposit on `main` to the transaction that relayed it on `side`
+#[derive(Debug, PartialEq, Clone)]
+pub struct RelayLink {
+    pub main_tx_hash: H256,
+    /// transaction that completed the relay or
+    /// the latest signature if it's still pending
+    pub side_tx_hash: H256,
+    pub relay_block: u64,
+    pub status: RelayStatus,
+}
+
 /// highlevel wrapper around the auto generated ethabi contract `bridge_contracts::side`
 #[derive(Clone)]
 pub struct SideContract<T> {
     pub transport: T,
+    /// used instead of `transport` to fetch logs if set
+    pub archive_transport: Option<T>,
     pub contract_address: Address,
     pub authority_address: Address,
     // TODO [snd] this should get fetched from the contract
     pub required_signatures: u32,
+    /// if set only deposits from these addresses are signed.
+    /// shared by all clones so changes made through
+    /// `add_to_whitelist` and `remove_from_whitelist` apply to all of them
+    pub depositor_whitelist: Rc<RefCell<Option<Vec<Address>>>>,
     pub request_timeout: Duration,
     pub logs_poll_interval: Duration,
     pub required_log_confirmations: u32,
+    pub logs_page_size: u64,
+    pub logs_adaptive_polling: Option<AdaptivePolling>,
+    pub logs_poll_backoff: Option<PollBackoff>,
     pub sign_main_to_side_gas: U256,
     pub sign_main_to_side_gas_price: U256,
     pub sign_side_to_main_gas: U256,
@@ -47,12 +84,17 @@ impl<T: Transport> SideContract<T> {
     pub fn new(transport: T, config: &Config, state: &State) -> Self {
         Self {
             transport,
+            archive_transport: None,
             contract_address: state.side_contract_address,
             authority_address: config.address,
             required_signatures: config.authorities.required_signatures,
+            depositor_whitelist: Rc::new(RefCell::new(config.depositor_whitelist.clone())),
             request_timeout: config.side.request_timeout,
             logs_poll_interval: config.side.poll_interval,
             required_log_confirmations: config.side.required_confirmations,
+            logs_page_size: config.side.log_filter_page_size,
+            logs_adaptive_polling: config.side.adaptive_polling.clone(),
+            logs_poll_backoff: config.side.poll_backoff.clone(),
             sign_main_to_side_gas: config.txs.deposit_relay.gas,
             sign_main_to_side_gas_price: config.txs.deposit_relay.gas_price,
             sign_side_to_main_gas: config.txs.withdraw_confirm.gas,
@@ -60,6 +102,50 @@ impl<T: Transport> SideContract<T> {
         }
     }
 
+    /// fetch logs through `archive_transport` which may be connected to
+    /// an archive node while `transport` is connected to a full node
+    pub fn with_archive_transport(mut self, archive_transport: T) -> Self {
+        self.archive_transport = Some(archive_transport);
+        self
+    }
+
+    /// whether deposits from `depositor` should be signed
+    pub fn is_depositor_whitelisted(&self, depositor: Address) -> bool {
+        self.depositor_whitelist
+            .borrow()
+            .as_ref()
+            .map_or(true, |whitelist| whitelist.contains(&depositor))
+    }
+
+    /// signs deposits from `depositor` from now on.
+    /// enables the whitelist if it isn't set which stops
+    /// signing deposits from all other addresses
+    pub fn add_to_whitelist(&self, depositor: Address) {
+        let mut depositor_whitelist = self.depositor_whitelist.borrow_mut();
+        let whitelist = depositor_whitelist.get_or_insert_with(Vec::new);
+        if !whitelist.contains(&depositor) {
+            info!("adding {} to the depositor whitelist", to_checksum_address(&depositor));
+            whitelist.push(depositor);
+        }
+    }
+
+    /// stops signing deposits from `depositor`.
+    /// has no effect if the whitelist isn't set
+    pub fn remove_from_whitelist(&self, depositor: Address) {
+        if let Some(ref mut whitelist) = *self.depositor_whitelist.borrow_mut() {
+            if whitelist.contains(&depositor) {
+                info!("removing {} from the depositor whitelist", to_checksum_address(&depositor));
+                whitelist.retain(|address| *address != depositor);
+            }
+        }
+    }
+
+    fn logs_transport(&self) -> T {
+        self.archive_transport
+            .clone()
+            .unwrap_or_else(|| self.transport.clone())
+    }
+
     pub fn call<F: FunctionOutputDecoder>(&self, payload: Vec<u8>, output_decoder: F) -> AsyncCall<T, F> {
         AsyncCall::new(
             &self.transport,
@@ -130,9 +216,12 @@ impl<T: Transport> SideContract<T> {
             request_timeout: self.request_timeout,
             poll_interval: self.logs_poll_interval,
             confirmations: self.required_log_confirmations,
-            transport: self.transport.clone(),
+            transport: self.logs_transport(),
             contract_address: self.contract_address,
             after,
+            page_size: self.logs_page_size,
+            adaptive_polling: self.logs_adaptive_polling.clone(),
+            poll_backoff: self.logs_poll_backoff.clone(),
         })
     }
 
@@ -142,9 +231,12 @@ impl<T: Transport> SideContract<T> {
             request_timeout: self.request_timeout,
             poll_interval: self.logs_poll_interval,
             confirmations: self.required_log_confirmations,
-            transport: self.transport.clone(),
+            transport: self.logs_transport(),
             contract_address: self.contract_address,
             after,
+            page_size: self.logs_page_size,
+            adaptive_polling: self.logs_adaptive_polling.clone(),
+            poll_backoff: self.logs_poll_backoff.clone(),
         })
     }
 
@@ -165,6 +257,62 @@ impl<T: Transport> SideContract<T> {
         )
     }
 
+    /// returns a `Future` that searches the `Deposit` and `DepositConfirmation`
+    /// logs of the side contract since `from_block` for the relay of
+    /// the deposit in transaction `main_tx_hash` on `main`.
+    /// block ranges with more than `logs_page_size` logs are fetched in smaller chunks.
+    /// resolves with `None` if no authority has signed it yet.
+    pub fn find_relay_for_deposit(
+        &self,
+        main_tx_hash: H256,
+        from_block: u64,
+    ) -> impl Future<Item = Option<RelayLink>, Error = Error> {
+        let deposit_topic: Vec<H256> = contracts::side::events::deposit::filter().topic0.into();
+        let confirmation_topic: Vec<H256> =
+            contracts::side::events::deposit_confirmation::filter().topic0.into();
+        let topics = deposit_topic
+            .iter()
+            .chain(confirmation_topic.iter())
+            .cloned()
+            .collect();
+        let filter_builder = FilterBuilder::default()
+            .address(vec![self.contract_address])
+            .topics(Some(topics), None, None, None);
+        let transport = self.logs_transport();
+        let request_timeout = self.request_timeout;
+        let page_size = self.logs_page_size;
+        let timer = Timer::default();
+        let last_block = timer.timeout(
+            web3::api::Eth::new(&transport).block_number().from_err(),
+            request_timeout,
+        );
+        last_block
+            .and_then(move |last_block| {
+                let last_block = last_block.low_u64();
+                loop_fn((from_block, last_block, Vec::new()), move |(from, to, mut logs)| {
+                    let filter = filter_builder
+                        .clone()
+                        .from_block(BlockNumber::Number(from))
+                        .to_block(BlockNumber::Number(to))
+                        .build();
+                    let future = web3::api::Eth::new(&transport).logs(filter).from_err();
+                    timer.timeout(future, request_timeout).map(move |page: Vec<Log>| {
+                        if let Some(middle) = split_block_range(from, to, page.len() as u64, page_size) {
+                            return Loop::Continue((from, middle, logs));
+                        }
+                        logs.extend(page);
+                        if to < last_block {
+                            // the rest of the block range split off earlier
+                            Loop::Continue((to + 1, last_block, logs))
+                        } else {
+                            Loop::Break(logs)
+                        }
+                    })
+                })
+            })
+            .and_then(move |logs| relay_link(main_tx_hash, &deposit_topic, &logs))
+    }
+
     pub fn get_signatures(
         &self,
         message_hash: H256,
@@ -179,3 +327,254 @@ impl<T: Transport> SideContract<T> {
         join_all(futures)
     }
 }
+
+/// finds the relay of `main_tx_hash` in side contract `logs`
+/// which are either `Deposit` or `DepositConfirmation` logs
+fn relay_link(main_tx_hash: H256, deposit_topic: &[H256], logs: &[Log]) -> Result<Option<RelayLink>, Error> {
+    let mut confirmations = 0;
+    let mut latest_confirmation = None;
+    for log in logs {
+        let is_deposit = log.topics
+            .first()
+            .map_or(false, |topic| deposit_topic.contains(topic));
+        let transaction_hash = if is_deposit {
+            helpers::parse_log(contracts::side::events::deposit::parse_log, log)?.transaction_hash
+        } else {
+            helpers::parse_log(contracts::side::events::deposit_confirmation::parse_log, log)?
+                .transaction_hash
+        };
+        if transaction_hash != main_tx_hash {
+            continue;
+        }
+
+        let side_tx_hash = log.transaction_hash
+            .ok_or_else(|| "side contract log has no transaction hash")?;
+        let relay_block = log.block_number
+            .ok_or_else(|| "side contract log has no block number")?
+            .low_u64();
+        if is_deposit {
+            return Ok(Some(RelayLink {
+                main_tx_hash,
+                side_tx_hash,
+                relay_block,
+                status: RelayStatus::Completed,
+            }));
+        }
+        confirmations += 1;
+        latest_confirmation = Some((side_tx_hash, relay_block));
+    }
+
+    Ok(latest_confirmation.map(|(side_tx_hash, relay_block)| RelayLink {
+        main_tx_hash,
+        side_tx_hash,
+        relay_block,
+        status: RelayStatus::Pending { confirmations },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethabi;
+    use serde_json;
+    use tokio_core::reactor::Core;
+    use web3::types::Bytes;
+
+    fn side_log(topic: Vec<H256>, main_tx_hash: H256, side_tx_hash: H256, block_number: u64) -> Log {
+        Log {
+            address: "0000000000000000000000000000000000000dd1".into(),
+            topics: topic,
+            data: Bytes(ethabi::encode(&[
+                ethabi::Token::Address("aff3454fce5edbc8cca8697c15331677e6ebcccc".into()),
+                ethabi::Token::Uint(1000.into()),
+                ethabi::Token::FixedBytes(main_tx_hash.to_vec()),
+            ])),
+            transaction_hash: Some(side_tx_hash),
+            block_hash: None,
+            block_number: Some(block_number.into()),
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    #[test]
+    fn test_relay_link() {
+        let deposit_topic: Vec<H256> = contracts::side::events::deposit::filter().topic0.into();
+        let confirmation_topic: Vec<H256> =
+            contracts::side::events::deposit_confirmation::filter().topic0.into();
+
+        let main_tx_hash: H256 = "0x884edad9ce6fa2440d8a54cc123490eb96d2768479d49ff9c7366125a9424364".into();
+        let other_main_tx_hash: H256 = "0x0000000000000000000000000000000000000000000000000000000000000001".into();
+        let confirmation_tx_hash: H256 = "0x0000000000000000000000000000000000000000000000000000000000000002".into();
+        let deposit_tx_hash: H256 = "0x0000000000000000000000000000000000000000000000000000000000000003".into();
+
+        let mut logs = vec![
+            side_log(confirmation_topic.clone(), other_main_tx_hash, confirmation_tx_hash, 10),
+            side_log(confirmation_topic.clone(), main_tx_hash, confirmation_tx_hash, 11),
+        ];
+        assert_eq!(relay_link(main_tx_hash, &deposit_topic, &[]).unwrap(), None);
+        assert_eq!(
+            relay_link(main_tx_hash, &deposit_topic, &logs).unwrap(),
+            Some(RelayLink {
+                main_tx_hash,
+                side_tx_hash: confirmation_tx_hash,
+                relay_block: 11,
+                status: RelayStatus::Pending { confirmations: 1 },
+            })
+        );
+
+        logs.push(side_log(deposit_topic.clone(), main_tx_hash, deposit_tx_hash, 12));
+        assert_eq!(
+            relay_link(main_tx_hash, &deposit_topic, &logs).unwrap(),
+            Some(RelayLink {
+                main_tx_hash,
+                side_tx_hash: deposit_tx_hash,
+                relay_block: 12,
+                status: RelayStatus::Completed,
+            })
+        );
+    }
+
+    fn side_contract(transport: ::MockTransport) -> SideContract<::MockTransport> {
+        SideContract {
+            transport,
+            archive_transport: None,
+            contract_address: "0000000000000000000000000000000000000dd1".into(),
+            authority_address: "0000000000000000000000000000000000000001".into(),
+            required_signatures: 1,
+            depositor_whitelist: Default::default(),
+            request_timeout: Duration::from_secs(1),
+            logs_poll_interval: Duration::from_secs(1),
+            required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            sign_main_to_side_gas: 0.into(),
+            sign_main_to_side_gas_price: 0.into(),
+            sign_side_to_main_gas: 0.into(),
+            sign_side_to_main_gas_price: 0.into(),
+        }
+    }
+
+    #[test]
+    fn test_depositor_whitelist_is_shared_by_clones() {
+        let transport = mock_transport!();
+        let side_contract = side_contract(transport);
+        let clone = side_contract.clone();
+        let depositor: Address = "0000000000000000000000000000000000000002".into();
+        let other: Address = "0000000000000000000000000000000000000003".into();
+
+        // no whitelist signs all deposits
+        assert!(clone.is_depositor_whitelisted(depositor));
+        assert!(clone.is_depositor_whitelisted(other));
+        side_contract.remove_from_whitelist(depositor);
+        assert!(clone.is_depositor_whitelisted(depositor));
+
+        side_contract.add_to_whitelist(depositor);
+        side_contract.add_to_whitelist(depositor);
+        assert_eq!(*clone.depositor_whitelist.borrow(), Some(vec![depositor]));
+        assert!(clone.is_depositor_whitelisted(depositor));
+        assert!(!clone.is_depositor_whitelisted(other));
+
+        side_contract.remove_from_whitelist(depositor);
+        assert!(!clone.is_depositor_whitelisted(depositor));
+        assert!(!clone.is_depositor_whitelisted(other));
+    }
+
+    fn side_log_json(topic: H256, main_tx_hash: &str, side_tx_hash: &str, block_number: &str) -> serde_json::Value {
+        json!({
+            "address": "0x0000000000000000000000000000000000000dd1",
+            "topics": [topic],
+            "data": format!(
+                "0x000000000000000000000000aff3454fce5edbc8cca8697c15331677e6ebcccc00000000000000000000000000000000000000000000000000000000000003e8{}",
+                &main_tx_hash[2..]
+            ),
+            "type": "",
+            "blockNumber": block_number,
+            "transactionHash": side_tx_hash
+        })
+    }
+
+    #[test]
+    fn test_find_relay_for_deposit_splits_block_range_with_too_many_logs() {
+        let deposit_topic: Vec<H256> = contracts::side::events::deposit::filter().topic0.into();
+        let confirmation_topic: Vec<H256> =
+            contracts::side::events::deposit_confirmation::filter().topic0.into();
+        let topics = json!([[deposit_topic[0], confirmation_topic[0]]]);
+
+        let main_tx_hash = "0x884edad9ce6fa2440d8a54cc123490eb96d2768479d49ff9c7366125a9424364";
+        let other_main_tx_hash = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        let confirmation_tx_hash = "0x0000000000000000000000000000000000000000000000000000000000000002";
+        let deposit_tx_hash = "0x0000000000000000000000000000000000000000000000000000000000000003";
+        let confirmation = side_log_json(confirmation_topic[0], main_tx_hash, confirmation_tx_hash, "0x4");
+        let other_confirmation = side_log_json(confirmation_topic[0], other_main_tx_hash, confirmation_tx_hash, "0x5");
+        let deposit = side_log_json(deposit_topic[0], main_tx_hash, deposit_tx_hash, "0x6");
+
+        let transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x6");
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000dd1",
+                    "fromBlock": "0x3",
+                    "toBlock": "0x6",
+                    "topics": topics,
+                }]),
+                res => json!([confirmation, other_confirmation, deposit]);
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000dd1",
+                    "fromBlock": "0x3",
+                    "toBlock": "0x4",
+                    "topics": topics,
+                }]),
+                res => json!([confirmation]);
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000dd1",
+                    "fromBlock": "0x5",
+                    "toBlock": "0x6",
+                    "topics": topics,
+                }]),
+                res => json!([other_confirmation, deposit]);
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000dd1",
+                    "fromBlock": "0x5",
+                    "toBlock": "0x5",
+                    "topics": topics,
+                }]),
+                res => json!([other_confirmation]);
+            "eth_getLogs" =>
+                req => json!([{
+                    "address": "0x0000000000000000000000000000000000000dd1",
+                    "fromBlock": "0x6",
+                    "toBlock": "0x6",
+                    "topics": topics,
+                }]),
+                res => json!([deposit]);
+        );
+
+        let mut side_contract = side_contract(transport.clone());
+        side_contract.logs_page_size = 1;
+
+        let mut event_loop = Core::new().unwrap();
+        let relay = event_loop
+            .run(side_contract.find_relay_for_deposit(main_tx_hash.into(), 3))
+            .unwrap();
+        assert_eq!(
+            relay,
+            Some(RelayLink {
+                main_tx_hash: main_tx_hash.into(),
+                side_tx_hash: deposit_tx_hash.into(),
+                relay_block: 6,
+                status: RelayStatus::Completed,
+            })
+        );
+        assert_eq!(transport.actual_requests(), transport.expected_requests());
+    }
+}
@@ -242,12 +242,17 @@ mod tests {
 
         let side_contract = SideContract {
             transport: transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
             sign_main_to_side_gas: 0.into(),
             sign_main_to_side_gas_price: 0.into(),
             sign_side_to_main_gas: 0xfd.into(),
@@ -324,12 +329,17 @@ mod tests {
 
         let side_contract = SideContract {
             transport: transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
             sign_main_to_side_gas: 0.into(),
             sign_main_to_side_gas_price: 0.into(),
             sign_side_to_main_gas: 0xfd.into(),
@@ -38,7 +38,10 @@ enum State<T: Transport> {
         future: JoinAll<Vec<AsyncCall<T, contracts::side::functions::signature::Decoder>>>,
         message: MessageToMain,
     },
-    AwaitTxSent(AsyncTransaction<T>),
+    AwaitTxSent {
+        future: AsyncTransaction<T>,
+        message: MessageToMain,
+    },
 }
 
 /// `Future` that completes a transfer from side to main by calling
@@ -135,9 +138,15 @@ impl<T: Transport> Future for SideToMainSignatures<T> {
                         .map(|x| Signature::from_bytes(x))
                         .collect::<Result<_, _>>()?;
                     info!("{:?} - step 2/3 - message and {} signatures received. about to send transaction", self.side_tx_hash, signatures.len());
-                    State::AwaitTxSent(self.main.relay_side_to_main(&message, &signatures))
+                    State::AwaitTxSent {
+                        future: self.main.relay_side_to_main(&message, &signatures),
+                        message: message.clone(),
+                    }
                 }
-                State::AwaitTxSent(ref mut future) => {
+                State::AwaitTxSent {
+                    ref mut future,
+                    ref message,
+                } => {
                     let main_tx_hash = try_ready!(
                         future
                             .poll()
@@ -147,6 +156,20 @@ impl<T: Transport> Future for SideToMainSignatures<T> {
                         "{:?} - step 3/3 - DONE - transaction sent {:?}",
                         self.side_tx_hash, main_tx_hash
                     );
+                    if let Some(ref distribution) = self.main.fee_distribution {
+                        match self.main.relay_fee(message) {
+                            Some(fee) => for (recipient, value) in distribution.split(fee) {
+                                info!(
+                                    "{:?} - relay fee share of {:?}: {} wei",
+                                    self.side_tx_hash, recipient, value
+                                );
+                            },
+                            None => warn!(
+                                "{:?} - relay fee overflows for gas price {}. not splitting it",
+                                self.side_tx_hash, message.main_gas_price
+                            ),
+                        }
+                    }
                     return Ok(Async::Ready(Some(main_tx_hash)));
                 }
             };
@@ -269,22 +292,33 @@ mod tests {
 
         let main_contract = MainContract {
             transport: main_transport.clone(),
+            archive_transport: None,
             contract_address: main_contract_address,
             authority_address,
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            fee_distribution: None,
             submit_collected_signatures_gas: 0xfd.into(),
         };
 
         let side_contract = SideContract {
             transport: side_transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            fee_distribution: None,
             sign_main_to_side_gas: 0.into(),
             sign_main_to_side_gas_price: 0.into(),
             sign_side_to_main_gas: 0xfd.into(),
@@ -355,22 +389,33 @@ mod tests {
 
         let main_contract = MainContract {
             transport: main_transport.clone(),
+            archive_transport: None,
             contract_address: main_contract_address,
             authority_address,
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            fee_distribution: None,
             submit_collected_signatures_gas: 0xfd.into(),
         };
 
         let side_contract = SideContract {
             transport: side_transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            fee_distribution: None,
             sign_main_to_side_gas: 0.into(),
             sign_main_to_side_gas_price: 0.into(),
             sign_side_to_main_gas: 0xfd.into(),
@@ -444,22 +489,33 @@ mod tests {
 
         let main_contract = MainContract {
             transport: main_transport.clone(),
+            archive_transport: None,
             contract_address: main_contract_address,
             authority_address,
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            fee_distribution: None,
             submit_collected_signatures_gas: 0xfd.into(),
         };
 
         let side_contract = SideContract {
             transport: side_transport.clone(),
+            archive_transport: None,
             contract_address: side_contract_address,
             authority_address,
             required_signatures: 1,
+            depositor_whitelist: Default::default(),
             request_timeout: ::std::time::Duration::from_millis(0),
             logs_poll_interval: ::std::time::Duration::from_millis(0),
             required_log_confirmations: 0,
+            logs_page_size: 10000,
+            logs_adaptive_polling: None,
+            logs_poll_backoff: None,
+            fee_distribution: None,
             sign_main_to_side_gas: 0.into(),
             sign_main_to_side_gas_price: 0.into(),
             sign_side_to_main_gas: 0xfd.into(),
@@ -0,0 +1,256 @@
+// Copyright 2017 Parity Technologies (UK) Ltd.
+// This file is part of Parity-Bridge.
+
+// Parity-Bridge is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity-Bridge is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity-Bridge.  If not, see <http://www.gnu.org/licenses/>.
+
+//! snapshot of how far the bridge processes are behind the chains
+
+use database::State;
+use error::{self, ResultExt};
+use futures::{Future, Stream};
+use std::cell::RefCell;
+use std::fmt;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio_core::net::TcpListener;
+use tokio_core::reactor::Handle;
+use tokio_io::io::{read, write_all};
+use tokio_timer::Timer;
+use web3;
+use web3::Transport;
+
+/// current block numbers of both chains next to the
+/// last blocks checked by each bridge process
+#[derive(Debug, PartialEq, Clone)]
+pub struct BridgeStatus {
+    /// number of the latest block of the main chain
+    pub main_block: u64,
+    /// number of the latest block of the side chain
+    pub side_block: u64,
+    /// number of last main block which has been checked for deposit relays
+    pub last_main_to_side_sign_at_block: u64,
+    /// number of last side block which has been checked for withdraw confirms
+    pub last_side_to_main_sign_at_block: u64,
+    /// number of last side block which has been checked for withdraw relays
+    pub last_side_to_main_signatures_at_block: u64,
+}
+
+impl BridgeStatus {
+    pub fn new(state: &State, main_block: u64, side_block: u64) -> Self {
+        Self {
+            main_block,
+            side_block,
+            last_main_to_side_sign_at_block: state.last_main_to_side_sign_at_block,
+            last_side_to_main_sign_at_block: state.last_side_to_main_sign_at_block,
+            last_side_to_main_signatures_at_block: state.last_side_to_main_signatures_at_block,
+        }
+    }
+
+    /// number of main blocks not yet checked for deposit relays
+    pub fn main_to_side_sign_lag(&self) -> u64 {
+        self.main_block
+            .saturating_sub(self.last_main_to_side_sign_at_block)
+    }
+
+    /// number of side blocks not yet checked for withdraw confirms
+    pub fn side_to_main_sign_lag(&self) -> u64 {
+        self.side_block
+            .saturating_sub(self.last_side_to_main_sign_at_block)
+    }
+
+    /// number of side blocks not yet checked for withdraw relays
+    pub fn side_to_main_signatures_lag(&self) -> u64 {
+        self.side_block
+            .saturating_sub(self.last_side_to_main_signatures_at_block)
+    }
+}
+
+impl fmt::Display for BridgeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "main block: {}", self.main_block)?;
+        writeln!(f, "side block: {}", self.side_block)?;
+        writeln!(
+            f,
+            "main to side sign: at block {} ({} blocks behind)",
+            self.last_main_to_side_sign_at_block,
+            self.main_to_side_sign_lag()
+        )?;
+        writeln!(
+            f,
+            "side to main sign: at block {} ({} blocks behind)",
+            self.last_side_to_main_sign_at_block,
+            self.side_to_main_sign_lag()
+        )?;
+        write!(
+            f,
+            "side to main signatures: at block {} ({} blocks behind)",
+            self.last_side_to_main_signatures_at_block,
+            self.side_to_main_signatures_lag()
+        )
+    }
+}
+
+/// returns a `Future` that fetches the latest block numbers of both chains
+/// and resolves with the status of the bridge in `state`
+pub fn bridge_status<T: Transport>(
+    state: &State,
+    main_transport: &T,
+    main_timeout: Duration,
+    side_transport: &T,
+    side_timeout: Duration,
+) -> impl Future<Item = BridgeStatus, Error = error::Error> {
+    let state = state.clone();
+    let main = Timer::default().timeout(
+        web3::api::Eth::new(main_transport)
+            .block_number()
+            .from_err(),
+        main_timeout,
+    );
+    let side = Timer::default().timeout(
+        web3::api::Eth::new(side_transport)
+            .block_number()
+            .from_err(),
+        side_timeout,
+    );
+    main.join(side).map(move |(main_block, side_block)| {
+        BridgeStatus::new(&state, main_block.as_u64(), side_block.as_u64())
+    })
+}
+
+/// `HTTP/1.1` response with `status` as plain text body.
+/// `503 Service Unavailable` if the status couldn't be fetched
+pub fn http_response(status: &Result<BridgeStatus, error::Error>) -> String {
+    let (status_line, body) = match *status {
+        Ok(ref status) => ("200 OK", status.to_string()),
+        Err(ref err) => ("503 Service Unavailable", err.to_string()),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status_line,
+        body.len(),
+        body
+    )
+}
+
+/// answers every connection to `addr` with the status of the bridge in `state`.
+/// runs on the event loop of `handle` until it stops.
+/// `state` is read for every connection and never written
+pub fn serve_status<T: Transport + 'static>(
+    addr: &SocketAddr,
+    handle: &Handle,
+    state: Rc<RefCell<State>>,
+    main_transport: T,
+    main_timeout: Duration,
+    side_transport: T,
+    side_timeout: Duration,
+) -> Result<(), error::Error> {
+    let listener =
+        TcpListener::bind(addr, handle).chain_err(|| format!("Cannot listen on {}", addr))?;
+    let connection_handle = handle.clone();
+    let server = listener
+        .incoming()
+        .for_each(move |(socket, _)| {
+            let status = bridge_status(
+                &state.borrow(),
+                &main_transport,
+                main_timeout,
+                &side_transport,
+                side_timeout,
+            );
+            // the request itself is ignored but read so closing
+            // the connection doesn't reset it before the response arrives
+            let response = read(socket, vec![0u8; 1024])
+                .map_err(error::Error::from)
+                .and_then(move |(socket, _, _)| {
+                    status
+                        .then(|status| Ok::<_, error::Error>(http_response(&status)))
+                        .and_then(move |response| write_all(socket, response).from_err())
+                })
+                .map(|_| ())
+                .map_err(|err| warn!("status request failed: {}", err));
+            connection_handle.spawn(response);
+            Ok(())
+        })
+        .map_err(|err| error!("status endpoint stopped: {}", err));
+    handle.spawn(server);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_core::reactor::Core;
+
+    #[test]
+    fn test_bridge_status() {
+        let main_transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x3e8");
+        );
+        let side_transport = mock_transport!(
+            "eth_blockNumber" =>
+                req => json!([]),
+                res => json!("0x64");
+        );
+        let state = State {
+            last_main_to_side_sign_at_block: 990,
+            last_side_to_main_sign_at_block: 100,
+            last_side_to_main_signatures_at_block: 120,
+            ..State::default()
+        };
+
+        let mut event_loop = Core::new().unwrap();
+        let status = event_loop
+            .run(bridge_status(
+                &state,
+                &main_transport,
+                Duration::from_secs(1),
+                &side_transport,
+                Duration::from_secs(1),
+            ))
+            .unwrap();
+
+        assert_eq!(status.main_block, 1000);
+        assert_eq!(status.side_block, 100);
+        assert_eq!(status.main_to_side_sign_lag(), 10);
+        assert_eq!(status.side_to_main_sign_lag(), 0);
+        // the database can be ahead of a node that is still syncing
+        assert_eq!(status.side_to_main_signatures_lag(), 0);
+
+        assert_eq!(main_transport.actual_requests(), main_transport.expected_requests());
+        assert_eq!(side_transport.actual_requests(), side_transport.expected_requests());
+    }
+
+    #[test]
+    fn test_http_response() {
+        let status = BridgeStatus {
+            main_block: 1000,
+            side_block: 100,
+            last_main_to_side_sign_at_block: 990,
+            last_side_to_main_sign_at_block: 100,
+            last_side_to_main_signatures_at_block: 100,
+        };
+        let body = status.to_string();
+        let response = http_response(&Ok(status));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(&format!("Content-Length: {}\r\n", body.len())));
+        assert!(response.ends_with(&format!("\r\n\r\n{}", body)));
+
+        let response = http_response(&Err(error::ErrorKind::TimedOut.into()));
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.ends_with("Request timed out"));
+    }
+}
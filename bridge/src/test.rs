@@ -24,6 +24,57 @@ use std::rc::Rc;
 use web3;
 use web3::Transport;
 
+/// use as mock response to make the request fail with a transport error
+pub const MOCK_TRANSPORT_ERROR: &str = "mock transport error";
+
+/// mock response for `eth_getBlockByNumber`
+pub fn block_json(number: &str, hash: &str, parent_hash: &str, transactions: serde_json::Value) -> serde_json::Value {
+    json!({
+        "hash": hash,
+        "parentHash": parent_hash,
+        "sha3Uncles": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "miner": "0x0000000000000000000000000000000000000000",
+        "stateRoot": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "transactionsRoot": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "receiptsRoot": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "number": number,
+        "gasUsed": "0x0",
+        "gasLimit": "0x0",
+        "extraData": "0x",
+        "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "timestamp": "0x0",
+        "difficulty": "0x0",
+        "totalDifficulty": "0x0",
+        "sealFields": [],
+        "uncles": [],
+        "transactions": transactions,
+        "size": "0x0"
+    })
+}
+
+/// mock response for `eth_getTransactionReceipt`.
+/// `block` is `(block_hash, block_number)` or `None` for a pending receipt
+pub fn receipt_json(
+    block: Option<(&str, &str)>,
+    transaction_hash: &str,
+    transaction_index: &str,
+    contract_address: &str,
+) -> serde_json::Value {
+    json!({
+        "blockHash": block.map(|b| b.0),
+        "blockNumber": block.map(|b| b.1),
+        "contractAddress": contract_address,
+        "cumulativeGasUsed": "0x1c1999",
+        "gasUsed": "0xcdb5d",
+        "logs": [],
+        "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "root": null,
+        "status": "0x1",
+        "transactionHash": transaction_hash,
+        "transactionIndex": transaction_index
+    })
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RequestData {
     pub method: String,
@@ -102,6 +153,10 @@ impl Transport for MockTransport {
             .iter()
             .nth(current_request_index - 1)
             .expect("missing response");
+        if response.as_str() == Some(MOCK_TRANSPORT_ERROR) {
+            let err = web3::ErrorKind::Transport(MOCK_TRANSPORT_ERROR.into()).into();
+            return Box::new(futures::failed(err));
+        }
         let f = futures::finished(response.clone());
         Box::new(f)
     }
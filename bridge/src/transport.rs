@@ -0,0 +1,129 @@
+// Copyright 2017 Parity Technologies (UK) Ltd.
+// This file is part of Parity-Bridge.
+
+// Parity-Bridge is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity-Bridge is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity-Bridge.  If not, see <http://www.gnu.org/licenses/>.
+
+//! connecting to a node through one of several transports
+
+use config::{redact_url, NodeConfig, TransportKind};
+use error::{Error, ResultExt};
+use jsonrpc_core::{Call, Value};
+use std::path::Path;
+use tokio_core::reactor::Handle;
+use web3;
+use web3::transports::{Http, Ipc, WebSocket};
+use web3::Transport;
+
+/// `Transport` to a node that is connected through http, websocket or ipc.
+/// `Bridge` uses a single transport type for both chains.
+/// this allows `main` and `side` to use different transports.
+#[derive(Debug, Clone)]
+pub enum NodeTransport {
+    Http(Http),
+    WebSocket(WebSocket),
+    Ipc(Ipc),
+}
+
+impl NodeTransport {
+    pub fn new_http(url: &str, handle: &Handle, max_parallel: usize) -> Result<Self, Error> {
+        let transport = Http::with_event_loop(url, handle, max_parallel)
+            .chain_err(|| format!("Cannot connect to node at {}", redact_url(url)))?;
+        Ok(NodeTransport::Http(transport))
+    }
+
+    /// fails if `url` isn't a `ws://` or `wss://` url
+    pub fn new_ws(url: &str, handle: &Handle) -> Result<Self, Error> {
+        if !is_websocket_url(url) {
+            bail!(
+                "{} is not a websocket url. websocket urls start with `ws://` or `wss://`",
+                redact_url(url)
+            );
+        }
+        let transport = WebSocket::with_event_loop(url, handle)
+            .chain_err(|| format!("Cannot connect to node at {}", redact_url(url)))?;
+        Ok(NodeTransport::WebSocket(transport))
+    }
+
+    pub fn new_ipc<P: AsRef<Path>>(path: P, handle: &Handle) -> Result<Self, Error> {
+        let transport = Ipc::with_event_loop(path.as_ref(), handle)
+            .chain_err(|| format!("Cannot connect to node at {:?}", path.as_ref()))?;
+        Ok(NodeTransport::Ipc(transport))
+    }
+
+    /// connects through the transport selected by `node.transport`.
+    /// `main` and `side` can use different transports.
+    pub fn from_node_config(
+        node: &NodeConfig,
+        handle: &Handle,
+        max_parallel: usize,
+    ) -> Result<Self, Error> {
+        match node.transport {
+            TransportKind::Http => Self::new(&node.http, handle, max_parallel),
+            TransportKind::Ws => match node.websocket {
+                Some(ref url) => Self::new_ws(url, handle),
+                None => bail!("`transport = \"ws\"` requires `websocket` to be set"),
+            },
+            TransportKind::Ipc => match node.ipc {
+                Some(ref path) => Self::new_ipc(path, handle),
+                None => bail!("`transport = \"ipc\"` requires `ipc` to be set"),
+            },
+        }
+    }
+
+    /// connects through websocket if `url` is a websocket url and through http otherwise
+    pub fn new(url: &str, handle: &Handle, max_parallel: usize) -> Result<Self, Error> {
+        if is_websocket_url(url) {
+            Self::new_ws(url, handle)
+        } else {
+            Self::new_http(url, handle, max_parallel)
+        }
+    }
+}
+
+pub fn is_websocket_url(url: &str) -> bool {
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+impl Transport for NodeTransport {
+    type Out = web3::Result<Value>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (usize, Call) {
+        match *self {
+            NodeTransport::Http(ref transport) => transport.prepare(method, params),
+            NodeTransport::WebSocket(ref transport) => transport.prepare(method, params),
+            NodeTransport::Ipc(ref transport) => transport.prepare(method, params),
+        }
+    }
+
+    fn send(&self, id: usize, request: Call) -> Self::Out {
+        match *self {
+            NodeTransport::Http(ref transport) => Box::new(transport.send(id, request)),
+            NodeTransport::WebSocket(ref transport) => Box::new(transport.send(id, request)),
+            NodeTransport::Ipc(ref transport) => Box::new(transport.send(id, request)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_websocket_url() {
+        assert!(is_websocket_url("ws://localhost:8546"));
+        assert!(is_websocket_url("wss://example.com/ws"));
+        assert!(!is_websocket_url("http://localhost:8545"));
+        assert!(!is_websocket_url("https://example.com"));
+    }
+}
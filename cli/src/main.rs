@@ -27,22 +27,31 @@ extern crate web3;
 
 use docopt::Docopt;
 use futures::Stream;
+use std::cell::RefCell;
 use std::env;
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
 use tokio_core::reactor::Core;
-use web3::transports::http::Http;
 
-use bridge::config::Config;
-use bridge::database::{Database, TomlFileDatabase};
+use bridge::config::{redact_url, Config};
+use bridge::database::{Database, State, TomlFileDatabase};
 use bridge::error::{self, ResultExt};
 use bridge::helpers::StreamExt;
+use bridge::transport::NodeTransport;
 
 const MAX_PARALLEL_REQUESTS: usize = 10;
+const STORAGE_BENCHMARK_ITERATIONS: u32 = 10;
 
 #[derive(Debug, Deserialize)]
 pub struct Args {
+    cmd_lint: bool,
+    cmd_find_deposit: bool,
+    cmd_status: bool,
     arg_config: PathBuf,
     arg_database: PathBuf,
+    arg_main_tx_hash: String,
 }
 
 fn main() {
@@ -51,7 +60,10 @@ fn main() {
 
     match result {
         Ok(s) => println!("{}", s),
-        Err(err) => print_err(err),
+        Err(err) => {
+            print_err(err);
+            process::exit(1);
+        }
     }
 }
 
@@ -60,7 +72,7 @@ fn print_err(err: error::Error) {
         .map(|e| e.to_string())
         .collect::<Vec<_>>()
         .join("\n\nCaused by:\n  ");
-    println!("{}", message);
+    eprintln!("{}", message);
 }
 
 fn execute<S, I>(command: I) -> Result<String, error::Error>
@@ -77,6 +89,9 @@ Parity-bridge
 
 Usage:
     parity-bridge --config <config> --database <database>
+    parity-bridge lint --config <config>
+    parity-bridge find-deposit --config <config> --database <database> <main-tx-hash>
+    parity-bridge status --config <config> --database <database>
     parity-bridge -h | --help
 
 Options:
@@ -92,48 +107,264 @@ Options:
         .map_err(|e| e.to_string())?;
 
     info!("Loading config from {:?}", args.arg_config);
-    let config = Config::load(&args.arg_config)?;
+    let mut config = Config::load_with_env(&args.arg_config)?;
+    info!("Loaded config:\n{}", config.redact());
+
+    if args.cmd_lint {
+        let warnings = config.lint();
+        if warnings.is_empty() {
+            return Ok("No warnings".into());
+        }
+        return Ok(warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"));
+    }
+
+    for warning in config.validate_cross_chain_consistency() {
+        warn!("{}", warning.message);
+    }
+
+    if let Some(warning) = config
+        .txs
+        .validate_gas_price_consistency(bridge::config::DEFAULT_MAX_GAS_PRICE_RATIO)
+    {
+        warn!("{}", warning.message);
+    }
+
+    // refuse to start before talking to any node
+    bridge::database::check_emergency_stop(&args.arg_database)?;
+
+    info!("Loading database from {:?}", args.arg_database);
+    let mut database = match (&config.main.existing_contract, &config.side.existing_contract) {
+        (&Some(ref main), &Some(ref side)) => TomlFileDatabase::load_or_default(
+            &args.arg_database,
+            State::from_existing_contracts(main, side),
+        )?,
+        _ => TomlFileDatabase::from_path(&args.arg_database)?,
+    };
+
+    info!("Reading initial state from database");
+    let initial_state = database.read();
 
     info!("Starting event loop");
     let mut event_loop = Core::new().unwrap();
 
+    let main_url = config.main.endpoint();
     info!(
-        "Establishing HTTP connection to parity node connected to main chain at {:?}",
-        config.main.http
+        "Establishing connection to parity node connected to main chain at {:?}",
+        redact_url(&main_url)
     );
-    let main_transport = Http::with_event_loop(
-        &config.main.http,
+    let main_transport = NodeTransport::from_node_config(
+        &config.main,
         &event_loop.handle(),
         MAX_PARALLEL_REQUESTS,
     ).chain_err(|| {
         format!(
             "Cannot connect to parity node connected to main chain at {}",
-            config.main.http
+            redact_url(&main_url)
         )
     })?;
 
+    let side_url = config.side.endpoint();
     info!(
-        "Establishing HTTP connection to parity node connected to side chain at {:?}",
-        config.side.http
+        "Establishing connection to parity node connected to side chain at {:?}",
+        redact_url(&side_url)
     );
-    let side_transport = Http::with_event_loop(
-        &config.side.http,
+    let side_transport = NodeTransport::from_node_config(
+        &config.side,
         &event_loop.handle(),
         MAX_PARALLEL_REQUESTS,
     ).chain_err(|| {
         format!(
             "Cannot connect to parity node connected to side chain at {}",
-            config.side.http
+            redact_url(&side_url)
         )
     })?;
 
-    info!("Loading database from {:?}", args.arg_database);
-    let mut database = TomlFileDatabase::from_path(&args.arg_database)?;
+    let main_archive_transport = match config.main.archive_http {
+        Some(ref url) => {
+            info!(
+                "Establishing connection to archive node connected to main chain at {:?}",
+                redact_url(url)
+            );
+            Some(NodeTransport::new(url, &event_loop.handle(), MAX_PARALLEL_REQUESTS)
+                .chain_err(|| {
+                    format!(
+                        "Cannot connect to archive node connected to main chain at {}",
+                        redact_url(url)
+                    )
+                })?)
+        }
+        None => None,
+    };
 
-    info!("Reading initial state from database");
-    let initial_state = database.read();
+    let side_archive_transport = match config.side.archive_http {
+        Some(ref url) => {
+            info!(
+                "Establishing connection to archive node connected to side chain at {:?}",
+                redact_url(url)
+            );
+            Some(NodeTransport::new(url, &event_loop.handle(), MAX_PARALLEL_REQUESTS)
+                .chain_err(|| {
+                    format!(
+                        "Cannot connect to archive node connected to side chain at {}",
+                        redact_url(url)
+                    )
+                })?)
+        }
+        None => None,
+    };
+
+    info!("Checking that both nodes respond");
+    event_loop.run(bridge::helpers::health_check(
+        &main_transport,
+        config.main.request_timeout,
+        &side_transport,
+        config.side.request_timeout,
+    ))?;
+
+    info!("Calibrating request timeouts");
+    for &mut (chain, transport, ref mut node) in [
+        ("main", &main_transport, &mut config.main),
+        ("side", &side_transport, &mut config.side),
+    ].iter_mut()
+    {
+        let timeout = event_loop.run(bridge::helpers::calibrate_timeout(
+            transport,
+            node.request_timeout,
+            bridge::helpers::TIMEOUT_CALIBRATION_SAMPLES,
+        ))?;
+        info!(
+            "{} request timeout: {:?} (configured: {:?})",
+            chain, timeout, node.request_timeout
+        );
+        node.request_timeout = timeout;
+    }
+
+    for &(chain, transport, node) in [
+        ("main", &main_transport, &config.main),
+        ("side", &side_transport, &config.side),
+    ].iter()
+    {
+        match node.chain_id {
+            Some(expected) => {
+                info!("Checking chain id of {} chain", chain);
+                event_loop.run(bridge::helpers::check_chain_id(
+                    transport,
+                    chain,
+                    expected,
+                    node.request_timeout,
+                ))?;
+            }
+            None => warn!(
+                "`{}.chain_id` is not set. set it to protect against connecting to the wrong chain",
+                chain
+            ),
+        }
+    }
 
-    let main_contract = bridge::MainContract::new(main_transport.clone(), &config, &initial_state);
+    if let Some(expected) = config.main.expected_genesis_hash {
+        info!("Checking genesis block hash of main chain");
+        event_loop.run(bridge::helpers::check_genesis_hash(
+            &main_transport,
+            "main",
+            expected,
+            config.main.request_timeout,
+        ))?;
+    }
+
+    if let Some(expected) = config.side.expected_genesis_hash {
+        info!("Checking genesis block hash of side chain");
+        event_loop.run(bridge::helpers::check_genesis_hash(
+            &side_transport,
+            "side",
+            expected,
+            config.side.request_timeout,
+        ))?;
+    }
+
+    if args.cmd_status {
+        let status = event_loop.run(bridge::status::bridge_status(
+            &initial_state,
+            &main_transport,
+            config.main.request_timeout,
+            &side_transport,
+            config.side.request_timeout,
+        ))?;
+        return Ok(status.to_string());
+    }
+
+    if let Some(max_write_latency) = config.max_db_write_latency {
+        info!("Benchmarking database storage");
+        let benchmark = bridge::database::benchmark_storage_io(
+            &initial_state,
+            &args.arg_database,
+            STORAGE_BENCHMARK_ITERATIONS,
+        )?;
+        info!("Database storage benchmark: {:?}", benchmark);
+        if benchmark.median_write > max_write_latency {
+            warn!(
+                "median database write took {:?} which is longer than `max_db_write_latency_ms` ({:?}). is the database on slow storage?",
+                benchmark.median_write, max_write_latency
+            );
+        }
+    }
+
+    info!("Verifying contract deployments against chains");
+    // archive nodes are more likely to still have old deploy blocks
+    event_loop.run(initial_state.verify_against_chain(
+        main_archive_transport.as_ref().unwrap_or(&main_transport),
+        config.main.request_timeout,
+        side_archive_transport.as_ref().unwrap_or(&side_transport),
+        config.side.request_timeout,
+    ))?;
+
+    let abis = [
+        (
+            "main",
+            main_archive_transport.as_ref().unwrap_or(&main_transport),
+            initial_state.main_contract_address,
+            bridge::abi_validation::MAIN_ABI,
+            config.main.request_timeout,
+        ),
+        (
+            "side",
+            side_archive_transport.as_ref().unwrap_or(&side_transport),
+            initial_state.side_contract_address,
+            bridge::abi_validation::SIDE_ABI,
+            config.side.request_timeout,
+        ),
+    ];
+    for &(chain, transport, address, abi, timeout) in abis.iter() {
+        let validation = event_loop.run(bridge::abi_validation::validate_abi_against_deployed_code(
+            transport, address, abi, timeout,
+        )?)?;
+        match validation {
+            bridge::abi_validation::AbiValidation::Valid => {}
+            bridge::abi_validation::AbiValidation::MissingFunctions(missing) => warn!(
+                "{} contract at {:?} doesn't seem to contain functions {:?} of the compiled ABI. was the contract upgraded?",
+                chain, address, missing
+            ),
+            // the database records a deployment at `address`.
+            // the contract must have self-destructed since
+            bridge::abi_validation::AbiValidation::NoCode => {
+                let reason = format!(
+                    "{} contract at {:?} has no code. it has self-destructed",
+                    chain, address
+                );
+                bridge::database::emergency_shutdown(&args.arg_database, &reason)?;
+                return Err(format!("emergency shutdown: {}", reason).into());
+            }
+        }
+    }
+
+    let mut main_contract =
+        bridge::MainContract::new(main_transport.clone(), &config, &initial_state);
+    if let Some(archive_transport) = main_archive_transport.clone() {
+        main_contract = main_contract.with_archive_transport(archive_transport);
+    }
     event_loop
         .run(main_contract.is_main_contract())
         .chain_err(|| {
@@ -144,7 +375,11 @@ Options:
         )
         })?;
 
-    let side_contract = bridge::SideContract::new(side_transport.clone(), &config, &initial_state);
+    let mut side_contract =
+        bridge::SideContract::new(side_transport.clone(), &config, &initial_state);
+    if let Some(archive_transport) = side_archive_transport.clone() {
+        side_contract = side_contract.with_archive_transport(archive_transport);
+    }
     event_loop
         .run(side_contract.is_side_contract())
         .chain_err(|| {
@@ -155,10 +390,42 @@ Options:
         )
         })?;
 
+    if args.cmd_find_deposit {
+        let hash = args.arg_main_tx_hash.trim_left_matches("0x");
+        let main_tx_hash = hash.parse::<web3::types::H256>()
+            .map_err(|_| format!("Cannot parse transaction hash {}", args.arg_main_tx_hash))?;
+        let relay = event_loop.run(
+            side_contract.find_relay_for_deposit(main_tx_hash, initial_state.side_deployed_at_block),
+        )?;
+        return Ok(match relay {
+            Some(relay) => format!(
+                "{:?} in side transaction {:?} at block {}",
+                relay.status, relay.side_tx_hash, relay.relay_block
+            ),
+            None => "Not relayed yet".into(),
+        });
+    }
+
+    let status_state = Rc::new(RefCell::new(initial_state.clone()));
+    if let Some(port) = config.status_port {
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        info!("Serving status at http://{}", addr);
+        bridge::status::serve_status(
+            &addr,
+            &event_loop.handle(),
+            status_state.clone(),
+            main_transport.clone(),
+            config.main.request_timeout,
+            side_transport.clone(),
+            config.side.request_timeout,
+        )?;
+    }
+
     let bridge_stream = bridge::Bridge::new(initial_state, main_contract, side_contract);
     info!("Started polling logs");
     let persisted_bridge_stream = bridge_stream.and_then(|state| {
         database.write(&state)?;
+        *status_state.borrow_mut() = state;
         // info!("state change: {}", state);
         Ok(())
     });
@@ -27,14 +27,16 @@ extern crate web3;
 
 use docopt::Docopt;
 use std::path::PathBuf;
+use std::process;
 use std::{env, fs};
 use tokio_core::reactor::Core;
-use web3::transports::http::Http;
 
-use bridge::config::Config;
+use bridge::config::{redact_url, Config};
 use bridge::database::State;
-use bridge::deploy::{DeployMain, DeploySide};
+use bridge::deploy::{dry_run_deploy, main_deploy_request, side_deploy_request, DeployMain,
+                     DeploySide};
 use bridge::error::{self, ResultExt};
+use bridge::transport::NodeTransport;
 
 const MAX_PARALLEL_REQUESTS: usize = 10;
 
@@ -42,6 +44,7 @@ const MAX_PARALLEL_REQUESTS: usize = 10;
 pub struct Args {
     arg_config: PathBuf,
     arg_database: PathBuf,
+    flag_dry_run: bool,
 }
 
 fn main() {
@@ -50,7 +53,10 @@ fn main() {
 
     match result {
         Ok(s) => println!("{}", s),
-        Err(err) => print_err(err),
+        Err(err) => {
+            print_err(err);
+            process::exit(1);
+        }
     }
 }
 
@@ -59,7 +65,7 @@ fn print_err(err: error::Error) {
         .map(|e| e.to_string())
         .collect::<Vec<_>>()
         .join("\n\nCaused by:\n	");
-    println!("{}", message);
+    eprintln!("{}", message);
 }
 
 fn execute<S, I>(command: I) -> Result<String, error::Error>
@@ -75,11 +81,13 @@ Parity-bridge
     Commit: {}
 
 Usage:
-    parity-bridge-deploy --config <config> --database <database>
+    parity-bridge-deploy --config <config> --database <database> [--dry-run]
     parity-bridge-deploy -h | --help
 
 Options:
     -h, --help           Display help message and exit.
+    --dry-run            Simulate the deployments with `eth_call` instead of sending transactions.
+                         Nothing is written to <database>.
 "#,
         env!("CARGO_PKG_VERSION"),
         env!("GIT_HASH")
@@ -91,32 +99,91 @@ Options:
         .map_err(|e| e.to_string())?;
 
     info!(target: "parity-bridge-deploy", "Loading config");
-    let config = Config::load(args.arg_config)?;
+    let config = Config::load_with_env(&args.arg_config)?;
 
     info!(target: "parity-bridge-deploy", "Starting event loop");
     let mut event_loop = Core::new().unwrap();
 
+    let main_url = config.main.endpoint();
     info!(
-        "Establishing HTTP connection to main {:?}",
-        config.main.http
+        target: "parity-bridge-deploy",
+        "Establishing connection to main {:?}",
+        redact_url(&main_url)
     );
-    let main_transport =
-        Http::with_event_loop(
-            &config.main.http,
-            &event_loop.handle(),
-            MAX_PARALLEL_REQUESTS,
-        ).chain_err(|| format!("Cannot connect to main at {}", config.main.http))?;
+    let main_transport = NodeTransport::from_node_config(
+        &config.main,
+        &event_loop.handle(),
+        MAX_PARALLEL_REQUESTS,
+    ).chain_err(|| format!("Cannot connect to main at {}", redact_url(&main_url)))?;
 
+    let side_url = config.side.endpoint();
     info!(
-        "Establishing HTTP connection to side {:?}",
-        config.side.http
+        target: "parity-bridge-deploy",
+        "Establishing connection to side {:?}",
+        redact_url(&side_url)
     );
-    let side_transport =
-        Http::with_event_loop(
-            &config.side.http,
-            &event_loop.handle(),
-            MAX_PARALLEL_REQUESTS,
-        ).chain_err(|| format!("Cannot connect to side at {}", config.side.http))?;
+    let side_transport = NodeTransport::from_node_config(
+        &config.side,
+        &event_loop.handle(),
+        MAX_PARALLEL_REQUESTS,
+    ).chain_err(|| format!("Cannot connect to side at {}", redact_url(&side_url)))?;
+
+    info!(target: "parity-bridge-deploy", "Checking that both nodes respond");
+    event_loop.run(bridge::helpers::health_check(
+        &main_transport,
+        config.main.request_timeout,
+        &side_transport,
+        config.side.request_timeout,
+    ))?;
+
+    for &(chain, transport, node) in [
+        ("main", &main_transport, &config.main),
+        ("side", &side_transport, &config.side),
+    ].iter()
+    {
+        match node.chain_id {
+            Some(expected) => {
+                info!(target: "parity-bridge-deploy", "Checking chain id of {} chain", chain);
+                event_loop.run(bridge::helpers::check_chain_id(
+                    transport,
+                    chain,
+                    expected,
+                    node.request_timeout,
+                ))?;
+            }
+            None => warn!(
+                target: "parity-bridge-deploy",
+                "`{}.chain_id` is not set. set it to protect against deploying to the wrong chain",
+                chain
+            ),
+        }
+    }
+
+    if args.flag_dry_run {
+        info!(target: "parity-bridge-deploy", "Simulating MainBridge deployment");
+        let main_code = event_loop
+            .run(dry_run_deploy(
+                &main_transport,
+                &main_deploy_request(&config),
+                config.main.request_timeout,
+            ))
+            .chain_err(|| "Simulating MainBridge deployment failed")?;
+
+        info!(target: "parity-bridge-deploy", "Simulating SideBridge deployment");
+        let side_code = event_loop
+            .run(dry_run_deploy(
+                &side_transport,
+                &side_deploy_request(&config),
+                config.side.request_timeout,
+            ))
+            .chain_err(|| "Simulating SideBridge deployment failed")?;
+
+        return Ok(format!(
+            "Dry run succeeded. MainBridge would have {} bytes of code. SideBridge would have {} bytes of code.",
+            main_code.0.len(),
+            side_code.0.len()
+        ));
+    }
 
     info!(target: "parity-bridge-deploy", "Deploying MainBridge contract");
     let main_deployed = event_loop.run(DeployMain::new(config.clone(), main_transport))?;
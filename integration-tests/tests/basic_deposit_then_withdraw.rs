@@ -63,6 +63,11 @@ fn parity_main_command() -> Command {
         .arg("0")
         .arg("--reseal-min-period")
         .arg("0")
+        // seal empty blocks so transactions get the
+        // `required_confirmations` without further transactions
+        .arg("--force-sealing")
+        .arg("--reseal-max-period")
+        .arg("100")
         .arg("--no-ws")
         .arg("--no-dapps")
         .arg("--no-warp")
@@ -90,6 +95,11 @@ fn parity_side_command() -> Command {
         .arg("0")
         .arg("--reseal-min-period")
         .arg("0")
+        // seal empty blocks so transactions get the
+        // `required_confirmations` without further transactions
+        .arg("--force-sealing")
+        .arg("--reseal-max-period")
+        .arg("100")
         .arg("--no-ws")
         .arg("--no-dapps")
         .arg("--no-warp")